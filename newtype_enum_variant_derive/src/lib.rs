@@ -0,0 +1,543 @@
+//! Derive macro for enums whose variants are all single-field newtypes and which are
+//! used as `#[serde(flatten)]` fields, e.g. `<Product><Dollars>6</Dollars></Product>`.
+//!
+//! The generated `Serialize` impl writes the variant as a single-entry map, so
+//! flattening it yields one `<Variant>inner</Variant>` element and using it as a
+//! plain field nests that element inside the field's own. The generated
+//! `Deserialize` impl reads the variant element through
+//! `newtype_enum_variant::newtype_variant_enum::flatten`, which copes with the way
+//! quick-xml buffers flattened content as text.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Fields, LitStr, Path, Type, parse_macro_input, parse_quote, spanned::Spanned,
+};
+
+/// Derives `serde::Serialize` and `serde::Deserialize` for a flattened newtype-variant enum,
+/// along with `flatten::FlattenedEnum` so siblings can tell its elements apart and
+/// `flatten::NewtypeEnum` for the adapters in `tagged`.
+///
+/// Variant attributes:
+/// - `#[flattened(rename = "...")]` changes the element name, which defaults to the
+///   variant's identifier.
+/// - `#[flattened(alias = "...")]` accepts another element name when reading.
+/// - `#[flattened(other)]` marks at most one variant whose inner type implements
+///   `flatten::OtherVariant` and so decides its element name at runtime.
+///
+/// Container attributes:
+/// - `#[flattened(serialize_name = "path")]` passes each element name through
+///   `fn(&'static str) -> &'static str` before writing it.
+/// - `#[flattened(crate = "path")]` names the `newtype_enum_variant` crate, for
+///   callers that reach it under another name. Generated code refers to
+///   `::newtype_enum_variant` by default, which `newtype_enum_variant` itself makes
+///   work from inside with `extern crate self as newtype_enum_variant`.
+#[proc_macro_derive(FlattenedNewtypeEnum, attributes(flattened))]
+pub fn derive_flattened_newtype_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Variant {
+    ident: syn::Ident,
+    name: String,
+    aliases: Vec<String>,
+    other: bool,
+    ty: Type,
+}
+
+#[derive(Default)]
+struct Container {
+    serialize_name: Option<Path>,
+    krate: Option<Path>,
+}
+
+impl Container {
+    /// Path of the runtime support module, `newtype_variant_enum::flatten`.
+    fn flatten_path(&self) -> TokenStream2 {
+        match &self.krate {
+            Some(krate) => quote!(#krate::newtype_variant_enum::flatten),
+            None => quote!(::newtype_enum_variant::newtype_variant_enum::flatten),
+        }
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "FlattenedNewtypeEnum can only be derived for enums",
+        ));
+    };
+
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let Fields::Unnamed(fields) = &variant.fields else {
+                return Err(syn::Error::new(
+                    variant.span(),
+                    "FlattenedNewtypeEnum variants must be newtypes, e.g. `Dollars(f32)`",
+                ));
+            };
+            let mut fields = fields.unnamed.iter();
+            let (Some(field), None) = (fields.next(), fields.next()) else {
+                return Err(syn::Error::new(
+                    variant.span(),
+                    "FlattenedNewtypeEnum variants must have exactly one field",
+                ));
+            };
+            parse_variant(variant, field.ty.clone())
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    if variants.iter().filter(|variant| variant.other).count() > 1 {
+        return Err(syn::Error::new(
+            input.span(),
+            "at most one variant can be #[flattened(other)]",
+        ));
+    }
+    let container = parse_container(&input)?;
+
+    let serialize = expand_serialize(&input, &container, &variants);
+    let deserialize = expand_deserialize(&input, &container, &variants);
+    let newtype_enum = expand_newtype_enum(&input, &container, &variants);
+    Ok(quote! {
+        #serialize
+        #deserialize
+        #newtype_enum
+    })
+}
+
+fn parse_container(input: &DeriveInput) -> syn::Result<Container> {
+    let mut container = Container::default();
+    for attr in &input.attrs {
+        if !attr.path().is_ident("flattened") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("serialize_name") {
+                let path = meta.value()?.parse::<LitStr>()?.parse::<Path>()?;
+                container.serialize_name = Some(path);
+                Ok(())
+            } else if meta.path.is_ident("crate") {
+                let path = meta.value()?.parse::<LitStr>()?.parse::<Path>()?;
+                container.krate = Some(path);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported flattened attribute"))
+            }
+        })?;
+    }
+    Ok(container)
+}
+
+fn parse_variant(variant: &syn::Variant, ty: Type) -> syn::Result<Variant> {
+    let mut parsed = Variant {
+        ident: variant.ident.clone(),
+        name: variant.ident.to_string(),
+        aliases: Vec::new(),
+        other: false,
+        ty,
+    };
+    for attr in &variant.attrs {
+        if !attr.path().is_ident("flattened") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                parsed.name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("alias") {
+                parsed
+                    .aliases
+                    .push(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("other") {
+                parsed.other = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported flattened attribute"))
+            }
+        })?;
+    }
+    Ok(parsed)
+}
+
+fn expand_serialize(
+    input: &DeriveInput,
+    container: &Container,
+    variants: &[Variant],
+) -> TokenStream2 {
+    let ident = &input.ident;
+    let mut generics = input.generics.clone();
+    for variant in variants.iter().filter(|variant| !variant.other) {
+        let ty = &variant.ty;
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#ty: ::serde::Serialize));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let flatten = container.flatten_path();
+
+    let arms = variants.iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        let (name, content) = if variant.other {
+            (
+                quote!(#flatten::OtherVariant::element_name(inner)),
+                quote!(&#flatten::OtherContent(inner)),
+            )
+        } else {
+            let name = &variant.name;
+            (quote!(#name), quote!(inner))
+        };
+        let name = match &container.serialize_name {
+            Some(path) => quote!(#path(#name)),
+            None => name,
+        };
+        quote! {
+            #ident::#variant_ident(inner) => {
+                let mut map = ::serde::Serializer::serialize_map(serializer, Some(1))?;
+                ::serde::ser::SerializeMap::serialize_entry(&mut map, #name, #content)?;
+                ::serde::ser::SerializeMap::end(map)
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::serde::Serialize for #ident #ty_generics #where_clause {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    }
+}
+
+fn expand_deserialize(
+    input: &DeriveInput,
+    container: &Container,
+    variants: &[Variant],
+) -> TokenStream2 {
+    let ident = &input.ident;
+    let enum_name = ident.to_string();
+    let mut generics = input.generics.clone();
+    for variant in variants.iter().filter(|variant| !variant.other) {
+        let ty = &variant.ty;
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#ty: ::serde::de::DeserializeOwned));
+    }
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let mut impl_generics = generics.clone();
+    impl_generics.params.insert(0, parse_quote!('de));
+    let (impl_generics, _, _) = impl_generics.split_for_impl();
+    let flatten = container.flatten_path();
+
+    let names = variants
+        .iter()
+        .filter(|variant| !variant.other)
+        .map(|variant| &variant.name);
+    let is_other = match variants.iter().find(|variant| variant.other) {
+        Some(variant) => {
+            let ty = &variant.ty;
+            quote!(<#ty as #flatten::OtherVariant>::is_element(key))
+        }
+        None => quote!(false),
+    };
+    let known = variants
+        .iter()
+        .filter(|variant| !variant.other)
+        .flat_map(|variant| std::iter::once(&variant.name).chain(&variant.aliases));
+    let (arms, fallback) = deserialize_arms(input, container, variants);
+
+    let (trait_generics, _, trait_where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #trait_generics #flatten::FlattenedEnum for #ident #ty_generics #trait_where_clause {
+            fn is_variant(key: &str) -> bool {
+                [#(#known),*].contains(&key) || #is_other
+            }
+        }
+
+        impl #impl_generics ::serde::Deserialize<'de> for #ident #ty_generics #where_clause {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                const VARIANTS: &[&str] = &[#(#names),*];
+                let (key, value) = #flatten::take_variant(
+                    deserializer,
+                    #enum_name,
+                    VARIANTS,
+                    <Self as #flatten::FlattenedEnum>::is_variant,
+                )?;
+                match key.as_str() {
+                    #(#arms)*
+                    #fallback
+                }
+            }
+        }
+    }
+}
+
+/// Match arms from a variant's element name and buffered `value` to the enum, with
+/// `key` holding the name, and the arm for names that are none of the variants'.
+fn deserialize_arms(
+    input: &DeriveInput,
+    container: &Container,
+    variants: &[Variant],
+) -> (Vec<TokenStream2>, TokenStream2) {
+    let ident = &input.ident;
+    let enum_name = ident.to_string();
+    let flatten = container.flatten_path();
+
+    let arms = variants
+        .iter()
+        .filter(|variant| !variant.other)
+        .map(|variant| {
+            let variant_ident = &variant.ident;
+            let name = &variant.name;
+            let aliases = &variant.aliases;
+            quote! {
+                #name #(| #aliases)* => value.deserialize_into(&key).map(#ident::#variant_ident),
+            }
+        })
+        .collect::<Vec<_>>();
+    let fallback = match variants.iter().find(|variant| variant.other) {
+        Some(variant) => {
+            let variant_ident = &variant.ident;
+            let ty = &variant.ty;
+            quote! {
+                _ => match <#ty as #flatten::OtherVariant>::from_element(&key, value) {
+                    ::core::option::Option::Some(result) => result
+                        .map(#ident::#variant_ident)
+                        .map_err(#flatten::ValueError::into_de),
+                    ::core::option::Option::None => {
+                        ::core::result::Result::Err(#flatten::unknown_variant(#enum_name, key))
+                    }
+                },
+            }
+        }
+        None => quote! {
+            _ => ::core::result::Result::Err(#flatten::unknown_variant(#enum_name, key)),
+        },
+    };
+    (arms, fallback)
+}
+
+/// Implements `flatten::NewtypeEnum`, which takes a value apart into its element name
+/// and content and puts it back together, for the tagged adapters.
+fn expand_newtype_enum(
+    input: &DeriveInput,
+    container: &Container,
+    variants: &[Variant],
+) -> TokenStream2 {
+    let ident = &input.ident;
+    let mut generics = input.generics.clone();
+    for variant in variants.iter().filter(|variant| !variant.other) {
+        let ty = &variant.ty;
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#ty: ::serde::Serialize + ::serde::de::DeserializeOwned));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let flatten = container.flatten_path();
+
+    let names = variants.iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        let (pattern, name) = if variant.other {
+            (
+                quote!(inner),
+                quote!(#flatten::OtherVariant::element_name(inner)),
+            )
+        } else {
+            let name = &variant.name;
+            (quote!(_), quote!(#name))
+        };
+        let name = match &container.serialize_name {
+            Some(path) => quote!(#path(#name)),
+            None => name,
+        };
+        quote!(#ident::#variant_ident(#pattern) => #name,)
+    });
+    let contents = variants.iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        if variant.other {
+            quote!(#ident::#variant_ident(inner) => #flatten::OtherVariant::serialize_content(inner, serializer),)
+        } else {
+            quote!(#ident::#variant_ident(inner) => ::serde::Serialize::serialize(inner, serializer),)
+        }
+    });
+    let (arms, fallback) = deserialize_arms(input, container, variants);
+
+    quote! {
+        impl #impl_generics #flatten::NewtypeEnum for #ident #ty_generics #where_clause {
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    #(#names)*
+                }
+            }
+
+            fn serialize_content<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                match self {
+                    #(#contents)*
+                }
+            }
+
+            fn from_variant(
+                key: ::std::string::String,
+                value: #flatten::Value,
+            ) -> ::core::result::Result<Self, #flatten::ValueError> {
+                match key.as_str() {
+                    #(#arms)*
+                    #fallback
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(input: DeriveInput) -> String {
+        expand(input).expect("should have expanded").to_string()
+    }
+
+    fn expand_err(input: DeriveInput) -> String {
+        match expand(input) {
+            Ok(tokens) => panic!("should have failed, expanded to {tokens}"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn element_names() {
+        let out = expand_str(parse_quote! {
+            enum Code {
+                #[flattened(rename = "GTIN", alias = "EAN", alias = "UPC")]
+                Gtin(u64),
+                Sku(String),
+            }
+        });
+        assert!(out.contains(r#""GTIN" | "EAN" | "UPC" =>"#), "{out}");
+        assert!(out.contains(r#""Sku" =>"#), "{out}");
+        assert!(!out.contains(r#""Gtin""#), "{out}");
+        // Aliases are read but never written.
+        assert!(
+            out.contains(r#"const VARIANTS : & [& str] = & ["GTIN" , "Sku"]"#),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn other_variant() {
+        let out = expand_str(parse_quote! {
+            enum Currency {
+                Dollars(f64),
+                #[flattened(other)]
+                Other(Money),
+            }
+        });
+        assert!(
+            out.contains("< Money as :: newtype_enum_variant :: newtype_variant_enum :: flatten :: OtherVariant > :: from_element"),
+            "{out}"
+        );
+        // The other variant's type need not implement serde's traits itself.
+        assert!(!out.contains("Money : :: serde"), "{out}");
+    }
+
+    #[test]
+    fn crate_path() {
+        let out = expand_str(parse_quote! {
+            #[flattened(crate = "::reexported::inner", serialize_name = "name_of")]
+            enum Currency {
+                Dollars(f64),
+            }
+        });
+        assert!(
+            out.contains(":: reexported :: inner :: newtype_variant_enum :: flatten"),
+            "{out}"
+        );
+        assert!(!out.contains(":: newtype_enum_variant ::"), "{out}");
+        assert!(out.contains(r#"name_of ("Dollars")"#), "{out}");
+    }
+
+    #[test]
+    fn misuse() {
+        assert_eq!(
+            expand_err(parse_quote!(
+                struct Price(f64);
+            )),
+            "FlattenedNewtypeEnum can only be derived for enums"
+        );
+        assert_eq!(
+            expand_err(parse_quote!(
+                enum Currency {
+                    Dollars { amount: f64 },
+                }
+            )),
+            "FlattenedNewtypeEnum variants must be newtypes, e.g. `Dollars(f32)`"
+        );
+        assert_eq!(
+            expand_err(parse_quote!(
+                enum Currency {
+                    Dollars(f64, f64),
+                }
+            )),
+            "FlattenedNewtypeEnum variants must have exactly one field"
+        );
+        assert_eq!(
+            expand_err(parse_quote!(
+                enum Currency {
+                    #[flattened(other)]
+                    Some(Money),
+                    #[flattened(other)]
+                    Other(Money),
+                }
+            )),
+            "at most one variant can be #[flattened(other)]"
+        );
+        assert_eq!(
+            expand_err(parse_quote!(
+                enum Currency {
+                    #[flattened(renamed = "USD")]
+                    Dollars(f64),
+                }
+            )),
+            "unsupported flattened attribute"
+        );
+        assert_eq!(
+            expand_err(parse_quote!(
+                #[flattened(rename = "Price")]
+                enum Currency {
+                    Dollars(f64),
+                }
+            )),
+            "unsupported flattened attribute"
+        );
+        assert_eq!(
+            expand_err(parse_quote!(
+                enum Currency {
+                    #[flattened(rename = USD)]
+                    Dollars(f64),
+                }
+            )),
+            "expected string literal"
+        );
+    }
+}
@@ -0,0 +1,5 @@
+// `#[derive(FlattenedNewtypeEnum)]` refers to this crate as `::newtype_enum_variant`
+// unless given `#[flattened(crate = "...")]`; this makes that path work from inside it.
+extern crate self as newtype_enum_variant;
+
+pub mod newtype_variant_enum;
@@ -1,58 +1,443 @@
-use std::path::PathBuf;
+//! Command-line tool for product documents.
+//!
+//! Run without arguments for usage. Exit codes let scripts tell failures apart:
+//! see [`usage`].
 
-use crate::newtype_variant_enum::{
+use std::{
+    env, fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+    process::ExitCode,
+    str::FromStr,
+};
+
+#[cfg(feature = "toml")]
+use newtype_enum_variant::newtype_variant_enum::toml::{self, TomlError};
+#[cfg(feature = "yaml")]
+use newtype_enum_variant::newtype_variant_enum::yaml::{self, YamlError};
+use newtype_enum_variant::newtype_variant_enum::{
     Product,
-    types::{Currency, Sale},
-    xml::{from_xml_file, to_xml_file},
+    catalog::{self, CatalogError, CatalogReader},
+    csv::{self, CsvError},
+    json::{self, JsonError},
+    types::Sale,
+    validate::Validate,
+    xml::{self, Indent, WriteOptions, XmlError},
 };
+use serde::Serialize;
+
+/// Names of the formats this build supports; `toml` and `yaml` are optional features.
+fn format_names() -> Vec<&'static str> {
+    ["xml", "json", "csv"]
+        .into_iter()
+        .chain(cfg!(feature = "toml").then_some("toml"))
+        .chain(cfg!(feature = "yaml").then_some("yaml"))
+        .collect()
+}
+
+fn usage() -> String {
+    let names = format_names();
+    let (last, rest) = names.split_last().expect("xml is always supported");
+    format!(
+        "\
+Usage:
+  newtype_enum_variant convert [--from FORMAT] [--to FORMAT] [INPUT] [OUTPUT]
+  newtype_enum_variant validate [--format FORMAT] FILE
+  newtype_enum_variant show [--format FORMAT] FILE
+  newtype_enum_variant roundtrip [--format FORMAT] FILE
+
+A file holds one product or a catalog of them.
+FORMAT is one of {} or {last}, and defaults to the file's
+extension. INPUT, OUTPUT and FILE may be `-` for stdin or stdout, which
+`convert` also uses when they are left out.
+
+Exit codes:
+  0  success
+  1  a product failed validation, or did not survive the round trip
+  2  bad command line
+  3  I/O error
+  4  the input could not be parsed, or the output could not be written",
+        rest.join(", ")
+    )
+}
+
+/// Why a command failed; each kind maps to its own exit code.
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Io(io::Error),
+    Parse(Box<dyn std::error::Error>),
+    Invalid(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Self::Invalid(_) => 1,
+            Self::Usage(_) => 2,
+            Self::Io(_) => 3,
+            Self::Parse(_) => 4,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Usage(msg) => write!(f, "{msg}\n\n{}", usage()),
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::Parse(err) => err.fmt(f),
+            Self::Invalid(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Sorts each format's errors into I/O failures and everything else.
+macro_rules! impl_from_format_error {
+    ($($ty:ident),* $(,)?) => {$(
+        impl From<$ty> for CliError {
+            fn from(err: $ty) -> Self {
+                match err {
+                    $ty::Io(err) => Self::Io(err),
+                    err => Self::Parse(err.into()),
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_format_error!(XmlError, JsonError, CsvError);
+#[cfg(feature = "toml")]
+impl_from_format_error!(TomlError);
+#[cfg(feature = "yaml")]
+impl_from_format_error!(YamlError);
+
+impl From<CatalogError> for CliError {
+    fn from(err: CatalogError) -> Self {
+        match err.source {
+            XmlError::Io(err) => Self::Io(err),
+            _ => Self::Parse(err.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Xml,
+    Json,
+    Csv,
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl FromStr for Format {
+    type Err = CliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "xml" => Ok(Self::Xml),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            #[cfg(feature = "toml")]
+            "toml" => Ok(Self::Toml),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Ok(Self::Yaml),
+            _ => Err(CliError::Usage(format!("unsupported format {s:?}"))),
+        }
+    }
+}
+
+impl Format {
+    /// `explicit` if given, or else the format named by `path`'s extension.
+    fn resolve(explicit: Option<Self>, path: &str) -> Result<Self, CliError> {
+        if let Some(format) = explicit {
+            return Ok(format);
+        }
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if path != "-" => ext.parse(),
+            _ => Err(CliError::Usage(format!(
+                "cannot tell the format of {path:?}, pass it explicitly"
+            ))),
+        }
+    }
+
+    /// Reads a single product, or every product of a catalog.
+    fn read(self, input: &str) -> Result<Vec<Product>, CliError> {
+        match self {
+            Self::Xml => {
+                let mut products = CatalogReader::new(input.as_bytes()).peekable();
+                if let Some(Err(CatalogError {
+                    source: XmlError::UnexpectedRoot { .. },
+                    ..
+                })) = products.peek()
+                {
+                    return Ok(vec![xml::from_str(input)?]);
+                }
+                Ok(products.collect::<Result<_, _>>()?)
+            }
+            Self::Json if input.trim_start().starts_with('[') => Ok(json::from_json_str(input)?),
+            Self::Json => Ok(vec![json::from_json_str(input)?]),
+            Self::Csv => Ok(csv::from_csv_str(input)?),
+            #[cfg(feature = "toml")]
+            Self::Toml if input.lines().any(|line| line.trim() == "[[Product]]") => {
+                Ok(toml::from_toml_str::<catalog::Catalog>(input)?.products)
+            }
+            #[cfg(feature = "toml")]
+            Self::Toml => Ok(vec![toml::from_toml_str(input)?]),
+            #[cfg(feature = "yaml")]
+            Self::Yaml if input.trim_start().starts_with('-') => Ok(yaml::from_yaml_str(input)?),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => Ok(vec![yaml::from_yaml_str(input)?]),
+        }
+    }
+
+    /// Writes a single product as one, and anything else as a catalog.
+    fn write(self, products: &[Product]) -> Result<String, CliError> {
+        #[derive(Serialize)]
+        struct CatalogRef<'a> {
+            #[serde(rename = "Product")]
+            products: &'a [Product],
+        }
+
+        let single = match products {
+            [product] => Some(product),
+            _ => None,
+        };
+        let catalog = CatalogRef { products };
+        let mut out = match self {
+            Self::Xml => {
+                let options = WriteOptions {
+                    root: if single.is_some() {
+                        catalog::PRODUCT
+                    } else {
+                        catalog::ROOT
+                    }
+                    .to_string(),
+                    indent: Some(Indent { char: ' ', size: 2 }),
+                    ..WriteOptions::default()
+                };
+                match single {
+                    Some(product) => xml::to_string_with(product, &options)?,
+                    None => xml::to_string_with(&catalog, &options)?,
+                }
+            }
+            Self::Json => {
+                let options = json::WriteOptions {
+                    pretty: true,
+                    ..json::WriteOptions::default()
+                };
+                match single {
+                    Some(product) => json::to_json_string_with(product, &options)?,
+                    None => json::to_json_string_with(&products, &options)?,
+                }
+            }
+            Self::Csv => return Ok(csv::to_csv_string(products)?),
+            #[cfg(feature = "toml")]
+            Self::Toml => match single {
+                Some(product) => toml::to_toml_string(product)?,
+                None => toml::to_toml_string(&catalog)?,
+            },
+            #[cfg(feature = "yaml")]
+            Self::Yaml => match single {
+                Some(product) => yaml::to_yaml_string(product)?,
+                None => yaml::to_yaml_string(&products)?,
+            },
+        };
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// Command-line arguments split into `--flag value` options and positionals.
+struct Args {
+    options: Vec<(String, String)>,
+    positional: Vec<String>,
+}
 
-#[allow(dead_code)]
-mod newtype_variant_enum;
+impl Args {
+    fn parse(args: impl Iterator<Item = String>, flags: &[&str]) -> Result<Self, CliError> {
+        let mut parsed = Self {
+            options: Vec::new(),
+            positional: Vec::new(),
+        };
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            if arg.starts_with("--") {
+                if !flags.contains(&arg.as_str()) {
+                    return Err(CliError::Usage(format!("unknown option {arg}")));
+                }
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("{arg} needs a value")))?;
+                parsed.options.push((arg, value));
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
 
-fn main() {
-    let file_path = PathBuf::from("test.xml");
-    let obj = Product {
-        name: "Scrub Daddy".to_string(),
-        price: Currency::Dollars(6.0),
-        sale: Some(Sale(25.5)),
-    };
+    fn format(&self, flag: &str) -> Result<Option<Format>, CliError> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(name, _)| name == flag)
+            .map(|(_, value)| value.parse())
+            .transpose()
+    }
 
-    println!("\n\nWith a rating:");
-    println!("\n[Original] {}, {:?}, {:?}", obj.name, obj.price, obj.sale);
+    /// The positionals, which must number between `min` and `max`.
+    fn positional(&self, min: usize, max: usize) -> Result<&[String], CliError> {
+        let count = self.positional.len();
+        if count < min || count > max {
+            return Err(CliError::Usage(format!(
+                "expected {min}..={max} arguments, got {count}"
+            )));
+        }
+        Ok(&self.positional)
+    }
+}
 
-    // Export
-    to_xml_file(&file_path, &obj).expect("should have written object to file");
-    println!("\n\n[EXPORT] Successfully wrote object to file.");
+/// `n` followed by `noun`, pluralized unless `n` is 1.
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{n} {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
 
-    // Import
-    let res = from_xml_file(&file_path).expect("should have read object into memory");
-    println!("\n\n[IMPORT] Successfully imported object from file.");
+fn read_input(path: &str) -> Result<String, CliError> {
+    if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        Ok(fs::read_to_string(path)?)
+    }
+}
 
-    assert_eq!(res, obj, "imported object does not match original");
-    println!("\n[IMPORT] Imported object matches original.");
+fn write_output(path: &str, output: &str) -> Result<(), CliError> {
+    if path == "-" {
+        io::stdout().write_all(output.as_bytes())?;
+    } else {
+        fs::write(path, output)?;
+    }
+    Ok(())
+}
 
-    println!("\n\n~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~\n\n");
+/// Reads `FILE` given as the only positional, in `--format` or its extension's format.
+fn read_file_argument(args: &Args) -> Result<(Format, Vec<Product>), CliError> {
+    let path = &args.positional(1, 1)?[0];
+    let format = Format::resolve(args.format("--format")?, path)?;
+    Ok((format, format.read(&read_input(path)?)?))
+}
 
-    let file_path = PathBuf::from("test.xml");
-    let obj = Product {
-        name: "F-22 Raptor".to_string(),
-        price: Currency::Dollars(350000000.0),
-        sale: None,
-    };
+fn convert(args: &Args) -> Result<(), CliError> {
+    let paths = args.positional(0, 2)?;
+    let input = paths.first().map_or("-", String::as_str);
+    let output = paths.get(1).map_or("-", String::as_str);
+    let from = Format::resolve(args.format("--from")?, input)?;
+    let to = Format::resolve(args.format("--to")?, output)?;
 
-    println!("Without a rating:");
-    println!("\n[Original] {}, {:?}, {:?}", obj.name, obj.price, obj.sale);
+    let products = from.read(&read_input(input)?)?;
+    write_output(output, &to.write(&products)?)
+}
 
-    // Export
-    to_xml_file(&file_path, &obj).expect("should have written object to file");
-    println!("\n\n[EXPORT] Successfully wrote object to file.");
+fn validate(args: &Args) -> Result<(), CliError> {
+    let (_, products) = read_file_argument(args)?;
+    let mut invalid = 0;
+    for (index, product) in products.iter().enumerate() {
+        if let Err(errors) = product.validate() {
+            invalid += 1;
+            for violation in &errors.0 {
+                eprintln!("product #{index} ({:?}): {violation}", product.name);
+            }
+        }
+    }
+    if invalid > 0 {
+        return Err(CliError::Invalid(format!(
+            "{invalid} of {} invalid",
+            count(products.len(), "product")
+        )));
+    }
+    println!("{} valid", count(products.len(), "product"));
+    Ok(())
+}
 
-    // Import
-    let res = from_xml_file(&file_path).expect("should have read object into memory");
-    println!("\n\n[IMPORT] Successfully imported object from file.");
+fn show(args: &Args) -> Result<(), CliError> {
+    let (_, products) = read_file_argument(args)?;
+    for product in &products {
+        println!("{}", product.name);
+        println!("  price: {}", product.price);
+        match &product.sale {
+            Some(sale) => {
+                match sale {
+                    Sale::PercentOff(percent) => println!("  sale:  {percent}% off"),
+                    Sale::AmountOff(amount) => println!("  sale:  {amount} off"),
+                    Sale::SalePrice(price) => println!("  sale:  now {price}"),
+                }
+                match product.effective_price() {
+                    Ok(price) => println!("  pay:   {price}"),
+                    Err(err) => println!("  pay:   ? ({err})"),
+                }
+            }
+            None => println!("  sale:  none"),
+        }
+    }
+    Ok(())
+}
 
-    assert_eq!(res, obj, "imported object does not match original");
-    println!("\n[IMPORT] Imported object matches original.");
+fn roundtrip(args: &Args) -> Result<(), CliError> {
+    let (format, products) = read_file_argument(args)?;
+    let written = format.write(&products)?;
+    let read_back = format.read(&written)?;
+    if read_back != products {
+        return Err(CliError::Invalid(format!(
+            "products changed on the round trip, they were written as:\n{written}"
+        )));
+    }
+    println!(
+        "{} survived the round trip",
+        count(products.len(), "product")
+    );
+    Ok(())
+}
+
+fn run() -> Result<(), CliError> {
+    let mut args = env::args().skip(1);
+    let command = args
+        .next()
+        .ok_or_else(|| CliError::Usage("missing command".to_string()))?;
+    match command.as_str() {
+        "convert" => convert(&Args::parse(args, &["--from", "--to"])?),
+        "validate" => validate(&Args::parse(args, &["--format"])?),
+        "show" => show(&Args::parse(args, &["--format"])?),
+        "roundtrip" => roundtrip(&Args::parse(args, &["--format"])?),
+        "help" | "--help" | "-h" => {
+            println!("{}", usage());
+            Ok(())
+        }
+        _ => Err(CliError::Usage(format!("unknown command {command:?}"))),
+    }
+}
 
-    println!("\n\n");
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(err.exit_code())
+        }
+    }
 }
@@ -1,152 +1,635 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::newtype_variant_enum::types::{
-    parse_sale_or_empty_string, serialize_currency::deserialize_flattened,
-};
+pub use newtype_enum_variant_derive::FlattenedNewtypeEnum;
 
+use crate::newtype_variant_enum::types::parse_sale_or_empty_string;
+
+/// Fields serde reports as missing or repeated are raised as [`types::FieldError`]s
+/// like the rest, see the `Deserialize` impl.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[serde(remote = "Self", rename_all = "PascalCase")]
 pub struct Product {
     pub name: String,
-    #[serde(flatten, deserialize_with = "deserialize_flattened")]
+    /// Read from either currency encoding, see [`types::currency_attribute`].
+    #[serde(
+        flatten,
+        serialize_with = "types::currency_attribute::serialize_flattened",
+        deserialize_with = "types::currency_attribute::deserialize_flattened"
+    )]
     pub price: types::Currency,
-    #[serde(deserialize_with = "parse_sale_or_empty_string")]
+    /// Read as `None` when missing, empty or `xsi:nil`; written following
+    /// [`types::with_no_sale`].
+    #[serde(
+        default,
+        deserialize_with = "parse_sale_or_empty_string",
+        serialize_with = "types::serialize_sale",
+        skip_serializing_if = "types::skip_sale"
+    )]
     pub sale: Option<types::Sale>,
+    /// Elements none of the above read, written back after them.
+    #[serde(
+        flatten,
+        deserialize_with = "extra::deserialize_besides::<_, types::currency_attribute::PriceElements>"
+    )]
+    pub extra: extra::Extra,
+}
+
+impl Serialize for Product {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Product::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Product {
+    /// Reads the fields into a [`flatten::Value`] first, so that serde's own missing
+    /// and duplicate field errors pass through [`flatten::ValueError`], which keeps
+    /// them as [`types::FieldError`]s.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = flatten::entries(deserializer)?;
+        Product::deserialize(flatten::ValueDeserializer::new(
+            flatten::Value::Map(entries),
+            "",
+        ))
+        .map_err(flatten::ValueError::into_de)
+    }
+}
+
+impl Product {
+    /// The price after applying the sale, if any.
+    pub fn effective_price(&self) -> Result<types::Currency, types::SaleError> {
+        match &self.sale {
+            Some(sale) => sale.apply(&self.price),
+            None => Ok(self.price.clone()),
+        }
+    }
 }
 
 pub mod types {
-    use serde::de::{Deserializer, Error};
+    use std::{
+        cell::Cell,
+        fmt,
+        num::{ParseFloatError, ParseIntError},
+        thread::LocalKey,
+    };
+
+    use derive_more::{Display, From};
+    use serde::{
+        Serializer,
+        de::{self, Deserializer},
+        ser::SerializeMap,
+    };
+
+    use super::{conversion, flatten::Value, *};
+
+    pub(crate) mod amount;
+    pub mod currency_attribute;
+    pub mod iso4217;
 
-    use super::*;
+    pub use amount::{Amount, ParseAmountError};
+    pub use currency_attribute::CurrencyEncoding;
+    pub use iso4217::{CurrencyCode, CurrencyNaming, Money};
 
-    #[derive(Debug, PartialEq, Serialize, Deserialize)]
-    pub struct Sale(pub f32);
+    /// Runs `f` with `key` set to `value`, restoring the previous value afterwards,
+    /// even if `f` panics. Backs every `with_*` setting, see [`iso4217::with_naming`].
+    pub(crate) fn scoped<T: Copy, R>(
+        key: &'static LocalKey<Cell<T>>,
+        value: T,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        struct Restore<T: Copy + 'static>(&'static LocalKey<Cell<T>>, T);
+        impl<T: Copy> Drop for Restore<T> {
+            fn drop(&mut self) {
+                self.0.set(self.1);
+            }
+        }
+
+        let _restore = Restore(key, key.replace(value));
+        f()
+    }
+
+    /// Money in a currency with two-digit minor units (cents).
+    pub type Cents = Amount<2>;
+
+    /// Percentage with two fraction digits, e.g. `25.50` for 25.5%.
+    pub type Percent = Amount<2>;
+
+    const HUNDRED_PERCENT: Percent = Percent::from_major(100);
+
+    /// A reduction of a product's price, written inside `<Sale>` as one variant
+    /// element, e.g. `<Sale><PercentOff>25.50</PercentOff></Sale>`.
+    #[derive(Debug, Clone, PartialEq, FlattenedNewtypeEnum)]
+    pub enum Sale {
+        /// Percentage taken off the price.
+        PercentOff(Percent),
+        /// Fixed amount taken off the price.
+        AmountOff(Currency),
+        /// Price charged instead of the regular one.
+        SalePrice(Currency),
+    }
+
+    /// Why a [`Sale`] cannot be applied to a price.
+    #[derive(Debug, Clone, PartialEq, Eq, Display)]
+    pub enum SaleError {
+        #[display("sale in {sale} cannot apply to a price in {price}")]
+        CurrencyMismatch { price: String, sale: String },
+        #[display("discount of {discount} exceeds the price of {price}")]
+        DiscountExceedsPrice { price: String, discount: String },
+        #[display("discount of {_0} is negative")]
+        NegativeDiscount(String),
+    }
+
+    impl std::error::Error for SaleError {}
 
-    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    impl Sale {
+        /// The price after this sale, rounded half-to-even to the price's minor units.
+        pub fn apply(&self, price: &Currency) -> Result<Currency, SaleError> {
+            let code = price.code();
+            let minor = price.minor_units();
+            let discounted = match self {
+                Self::PercentOff(percent) => {
+                    if *percent > HUNDRED_PERCENT {
+                        return Err(SaleError::DiscountExceedsPrice {
+                            price: price.to_string(),
+                            discount: format!("{percent}%"),
+                        });
+                    }
+                    if *percent < Percent::ZERO {
+                        return Err(SaleError::NegativeDiscount(format!("{percent}%")));
+                    }
+                    let kept = i128::from(HUNDRED_PERCENT.minor_units() - percent.minor_units());
+                    let hundred = i128::from(HUNDRED_PERCENT.minor_units());
+                    let minor = conversion::round_div(
+                        i128::from(minor) * kept,
+                        hundred,
+                        conversion::RoundingMode::HalfEven,
+                    );
+                    i64::try_from(minor).expect("a discounted price is no larger than the price")
+                }
+                Self::AmountOff(discount) | Self::SalePrice(discount) => {
+                    if discount.code() != code {
+                        return Err(SaleError::CurrencyMismatch {
+                            price: code.alpha.to_string(),
+                            sale: discount.code().alpha.to_string(),
+                        });
+                    }
+                    let off = match self {
+                        Self::AmountOff(_) => discount.minor_units(),
+                        _ => minor - discount.minor_units(),
+                    };
+                    if off < 0 {
+                        return Err(SaleError::NegativeDiscount(discount.to_string()));
+                    }
+                    if off > minor {
+                        return Err(SaleError::DiscountExceedsPrice {
+                            price: price.to_string(),
+                            discount: discount.to_string(),
+                        });
+                    }
+                    minor - off
+                }
+            };
+            Ok(Currency::from_minor(code, discounted))
+        }
+    }
+
+    #[derive(Debug, Clone, FlattenedNewtypeEnum)]
+    #[flattened(serialize_name = "currency_element_name")]
     pub enum Currency {
-        Dollars(f32),
-        Euros(f32),
+        #[flattened(alias = "USD")]
+        Dollars(Cents),
+        #[flattened(alias = "EUR")]
+        Euros(Cents),
+        /// Any other ISO 4217 currency, written under its code, e.g. `<JPY>1200</JPY>`.
+        ///
+        /// Reading never yields USD or EUR here, but nothing stops building it. Such a
+        /// value stands for the named variant: it compares equal to it and is written
+        /// under the same name, see [`Currency::from_minor`].
+        #[flattened(other)]
+        Other(Money),
     }
-    pub mod serialize_currency {
-        use super::*;
-        use serde::de::{self, MapAccess, Visitor};
-        use std::fmt;
 
-        #[derive(Deserialize)]
-        #[serde(untagged)]
-        enum TextOrMap {
-            Text(String),
-            Map {
-                #[serde(rename = "$text")]
-                text: String,
-            },
+    /// Compares by currency and amount, so `Other` holding USD equals `Dollars`.
+    impl PartialEq for Currency {
+        fn eq(&self, other: &Self) -> bool {
+            self.code().alpha == other.code().alpha && self.minor_units() == other.minor_units()
         }
+    }
 
-        struct ControlVisitor;
+    /// Currencies our documents named before we used ISO codes: (legacy name, code).
+    pub(crate) const LEGACY_NAMES: &[(&str, &str)] = &[("Dollars", "USD"), ("Euros", "EUR")];
 
-        impl<'de> Visitor<'de> for ControlVisitor {
-            type Value = Currency;
+    impl Currency {
+        /// Builds the amount for `code`, using the named variants for USD and EUR.
+        pub fn from_minor(code: &'static CurrencyCode, minor: i64) -> Self {
+            match code.alpha {
+                "USD" => Self::Dollars(Cents::from_minor(minor)),
+                "EUR" => Self::Euros(Cents::from_minor(minor)),
+                _ => Self::Other(Money::from_minor(code, minor)),
+            }
+        }
+
+        pub fn code(&self) -> &'static CurrencyCode {
+            let alpha = match self {
+                Self::Dollars(_) => "USD",
+                Self::Euros(_) => "EUR",
+                Self::Other(money) => return money.code(),
+            };
+            CurrencyCode::from_alpha(alpha).expect("legacy currencies are listed")
+        }
 
-            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-                f.write_str("Euros or Dollars element")
+        pub fn minor_units(&self) -> i64 {
+            match self {
+                Self::Dollars(amount) | Self::Euros(amount) => amount.minor_units(),
+                Self::Other(money) => money.minor_units(),
             }
+        }
 
-            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
-            where
-                M: MapAccess<'de>,
-            {
-                while let Some((key, tom)) = map.next_entry::<String, TextOrMap>()? {
-                    // Extract the string content
-                    let s = match tom {
-                        TextOrMap::Text(t) => t,
-                        TextOrMap::Map { text } => text,
-                    };
-                    let f = s.parse::<f32>().map_err(de::Error::custom)?;
-                    return match key.as_str() {
-                        "Euros" => Ok(Currency::Euros(f)),
-                        "Dollars" => Ok(Currency::Dollars(f)),
-                        _ => Err(de::Error::custom(format!("unexpected key {}", key))),
-                    };
+        /// Reads `amount` written under `element`, a legacy name such as `Dollars`
+        /// or an ISO code, the way the derived `Deserialize` impl does.
+        pub fn parse(element: &str, amount: &str) -> Result<Self, FieldError> {
+            let alpha = LEGACY_NAMES
+                .iter()
+                .find(|(legacy, _)| *legacy == element)
+                .map_or(element, |(_, code)| code);
+            let code =
+                CurrencyCode::from_alpha(alpha).ok_or_else(|| FieldError::UnknownVariant {
+                    enum_name: "Currency".to_string(),
+                    element: element.to_string(),
+                })?;
+            let money = Money::parse(code, amount).map_err(|err| FieldError::InvalidNumber {
+                element: element.to_string(),
+                value: amount.to_string(),
+                ty: format!("decimal({})", code.minor_units),
+                source: ParseNumberError::Decimal(err),
+            })?;
+            Ok(Self::from_minor(code, money.minor_units()))
+        }
+
+        /// Name this amount is written under, following [`iso4217::naming`].
+        pub fn element_name(&self) -> &'static str {
+            match self {
+                Self::Dollars(_) => currency_element_name("Dollars"),
+                Self::Euros(_) => currency_element_name("Euros"),
+                Self::Other(money) => currency_element_name(money.code().alpha),
+            }
+        }
+    }
+
+    impl fmt::Display for Currency {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            Money::from_minor(self.code(), self.minor_units()).fmt(f)
+        }
+    }
+
+    /// Element name for a currency variant under the naming set by the writer. Takes
+    /// either name of a currency that has a legacy one, as `Other` holds its code.
+    fn currency_element_name(name: &'static str) -> &'static str {
+        LEGACY_NAMES
+            .iter()
+            .find(|(legacy, code)| *legacy == name || *code == name)
+            .map_or(name, |(legacy, code)| match iso4217::naming() {
+                CurrencyNaming::Legacy => legacy,
+                CurrencyNaming::Iso => code,
+            })
+    }
+
+    /// Failures raised by the field deserializers in this crate.
+    ///
+    /// serde only lets a `Deserialize` impl report a message, so the `Display` form
+    /// below is what travels through the format's error type. The structured value
+    /// travels beside it: [`FieldError::raise`] keeps it on the raising thread, where
+    /// [`ValueError`](super::flatten::ValueError) and the format modules claim it.
+    #[derive(Debug)]
+    pub enum FieldError {
+        UnknownVariant {
+            enum_name: String,
+            element: String,
+        },
+        /// `element` is empty when raised by a type that does not know where it sits,
+        /// e.g. [`Amount`]; the caller fills it in.
+        InvalidNumber {
+            element: String,
+            value: String,
+            ty: String,
+            source: ParseNumberError,
+        },
+        Missing(String),
+        /// `second` came after `first` where only one of them may appear. Both are
+        /// the same when an element is simply repeated.
+        Duplicate {
+            first: String,
+            second: String,
+        },
+        /// An element nothing reads, rejected when reading strictly.
+        Unexpected(String),
+    }
+
+    impl fmt::Display for FieldError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Self::UnknownVariant { enum_name, element } => {
+                    write!(f, "unknown {enum_name} element `{element}`")
                 }
-                Err(de::Error::custom("expected <Euros> or <Dollars> element"))
+                Self::InvalidNumber {
+                    element,
+                    value,
+                    ty,
+                    source,
+                } => {
+                    write!(f, "invalid number `{value}`")?;
+                    if !element.is_empty() {
+                        write!(f, " in <{element}>")?;
+                    }
+                    write!(f, ", expected {ty}: {source}")
+                }
+                Self::Missing(element) => write!(f, "missing element <{element}>"),
+                Self::Duplicate { first, second } => {
+                    write!(f, "duplicate element <{second}>")?;
+                    if first != second {
+                        write!(f, " after <{first}>")?;
+                    }
+                    Ok(())
+                }
+                Self::Unexpected(element) => write!(f, "unexpected element <{element}>"),
             }
         }
+    }
 
-        pub fn deserialize_flattened<'de, D>(deserializer: D) -> Result<Currency, D::Error>
-        where
-            D: de::Deserializer<'de>,
-        {
-            deserializer.deserialize_map(ControlVisitor)
+    impl std::error::Error for FieldError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::InvalidNumber { source, .. } => Some(source),
+                Self::UnknownVariant { .. }
+                | Self::Missing(_)
+                | Self::Duplicate { .. }
+                | Self::Unexpected(_) => None,
+            }
         }
     }
 
-    pub fn parse_sale_or_empty_string<'de, D>(deserializer: D) -> Result<Option<Sale>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        // WORKING but not elegant - try this approach using an enum: https://users.rust-lang.org/t/serde-deserialize-empty-string-as-option-none/116201/2
-        match String::deserialize(deserializer) {
-            Ok(str) if str.is_empty() => Ok(None),
-            Ok(str) => Ok(Some(Sale(str.parse::<f32>().map_err(|err| {
-                D::Error::custom(format!("unexpected nonempty string: `{err}`"))
-            })?))),
-            Err(err) => Err(err),
+    thread_local! {
+        static RAISED: Cell<Option<FieldError>> = const { Cell::new(None) };
+    }
+
+    impl FieldError {
+        /// Fails a deserialization with this error, as `E` reporting its message.
+        ///
+        /// The error itself waits on this thread until [`take_raised`](Self::take_raised)
+        /// claims it, or another one is raised.
+        pub fn raise<E: de::Error>(self) -> E {
+            let message = self.to_string();
+            RAISED.set(Some(self));
+            E::custom(message)
+        }
+
+        /// Claims the error last raised on this thread if `message`, a failure's
+        /// message, reports it, on its own or with additions such as a position.
+        pub fn take_raised(message: &str) -> Option<Self> {
+            let raised = RAISED.take()?;
+            if message.contains(&raised.to_string()) {
+                Some(raised)
+            } else {
+                RAISED.set(Some(raised));
+                None
+            }
+        }
+
+        /// Runs `f`, a deserialization, returning the error it raised along with the
+        /// format's error if it failed because of one.
+        pub(crate) fn catch<T, E: fmt::Display>(
+            f: impl FnOnce() -> Result<T, E>,
+        ) -> Result<T, (E, Option<Self>)> {
+            RAISED.take();
+            f().map_err(|err| {
+                let raised = Self::take_raised(&err.to_string());
+                (err, raised)
+            })
+        }
+
+        /// Classifies a deserialization failure by the error it raised, if any, as
+        /// caught by [`catch`](Self::catch), or else as `other`, the format's own.
+        pub(crate) fn classify<X: FieldErrorVariants>(
+            raised: Option<Self>,
+            other: impl FnOnce() -> X,
+        ) -> X {
+            match raised {
+                Some(Self::UnknownVariant { enum_name, element }) if enum_name == "Currency" => {
+                    X::unknown_currency(element)
+                }
+                Some(Self::InvalidNumber {
+                    element,
+                    value,
+                    source,
+                    ..
+                }) => X::invalid_number(element, value, source),
+                Some(Self::Missing(element)) => X::missing(element),
+                Some(Self::Duplicate { first, second }) => X::duplicate(first, second),
+                Some(Self::Unexpected(element)) => X::unexpected(element),
+                Some(Self::UnknownVariant { .. }) | None => other(),
+            }
         }
     }
-}
 
-pub mod xml {
-    use std::{fs::File, io::BufReader, path::PathBuf};
+    /// The variants each format's error has for a [`FieldError`], naming what the
+    /// format calls an element: an element in XML, a field in JSON, a key otherwise.
+    pub(crate) trait FieldErrorVariants {
+        fn unknown_currency(name: String) -> Self;
+        fn invalid_number(name: String, value: String, source: ParseNumberError) -> Self;
+        fn missing(name: String) -> Self;
+        fn duplicate(first: String, second: String) -> Self;
+        fn unexpected(name: String) -> Self;
+    }
+
+    /// A [`FieldError::Duplicate`] in the words of a format whose elements are `what`s.
+    pub(crate) fn describe_duplicate(what: &str, first: &str, second: &str) -> String {
+        if first == second {
+            format!("duplicate {what} {second:?}")
+        } else {
+            format!("duplicate {what} {second:?} after {first:?}")
+        }
+    }
+
+    /// Why a number could not be parsed from element text.
+    #[derive(Debug, Clone, PartialEq, Display, From)]
+    pub enum ParseNumberError {
+        Float(ParseFloatError),
+        Int(ParseIntError),
+        Decimal(ParseAmountError),
+    }
+
+    impl std::error::Error for ParseNumberError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::Float(err) => Some(err),
+                Self::Int(err) => Some(err),
+                Self::Decimal(err) => Some(err),
+            }
+        }
+    }
+
+    /// How a product without a sale is written.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum NoSale {
+        /// No `<Sale>` element at all.
+        Omit,
+        /// `<Sale/>`.
+        #[default]
+        Empty,
+        /// `<Sale xmlns:xsi="..." xsi:nil="true"/>`.
+        Nil,
+    }
+
+    /// Namespace of the `xsi:nil` attribute.
+    pub const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+    thread_local! {
+        static NO_SALE: Cell<NoSale> = const { Cell::new(NoSale::Empty) };
+    }
+
+    /// Runs `f` with `policy` in effect for every product without a sale it
+    /// serializes, like [`iso4217::with_naming`].
+    pub fn with_no_sale<T>(policy: NoSale, f: impl FnOnce() -> T) -> T {
+        scoped(&NO_SALE, policy, f)
+    }
 
-    use super::Product;
+    /// The policy in effect on this thread, see [`with_no_sale`].
+    pub fn no_sale() -> NoSale {
+        NO_SALE.get()
+    }
 
-    pub fn from_xml_file(file_path: impl Into<PathBuf>) -> Result<Product, String> {
-        let file_path = file_path.into();
-        let source: File = File::open(&file_path)
-            .map_err(|e| format!("failed to open file: {:?}", e.to_string()))?;
-        let reader: BufReader<File> = BufReader::new(source);
+    thread_local! {
+        static BARE_PERCENT: Cell<bool> = const { Cell::new(false) };
+    }
 
-        let output: Product = quick_xml::de::from_reader(reader)
-            .map_err(|e| format!("failed to deserialize: {:?}", e.to_string()))?;
+    /// Runs `f` with every [`Sale::PercentOff`] written as the bare percentage,
+    /// `"Sale": 25.5`, the shape JSON documents had before sales had kinds.
+    pub(crate) fn with_bare_percent<T>(f: impl FnOnce() -> T) -> T {
+        scoped(&BARE_PERCENT, true, f)
+    }
 
-        Ok(output)
+    pub fn skip_sale(sale: &Option<Sale>) -> bool {
+        sale.is_none() && no_sale() == NoSale::Omit
     }
 
-    pub fn to_xml_file(file_path: impl Into<PathBuf>, obj: &Product) -> Result<File, String> {
-        let file: File = File::create(file_path.into())
-            .map_err(|e| format!("failed to create file: {:?}", e.to_string()))?;
-        let mut writer: quick_xml::Writer<&File> = quick_xml::Writer::new(&file);
+    pub fn serialize_sale<S: Serializer>(
+        sale: &Option<Sale>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match (sale, no_sale()) {
+            (Some(Sale::PercentOff(percent)), _) if BARE_PERCENT.get() => {
+                serializer.serialize_some(percent)
+            }
+            (Some(sale), _) => serializer.serialize_some(sale),
+            (None, NoSale::Nil) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("@xmlns:xsi", XSI_NAMESPACE)?;
+                map.serialize_entry("@xsi:nil", "true")?;
+                map.end()
+            }
+            (None, _) => serializer.serialize_none(),
+        }
+    }
 
-        writer
-            .write_serializable("DeviceTag", obj)
-            .map_err(|e| format!("failed to serialize: {:?}", e.to_string()))?;
+    /// Whether an element's content says `xsi:nil="true"`, under any prefix:
+    /// quick-xml drops it unless the document was read namespace-aware.
+    fn is_nil(value: &Value) -> bool {
+        let Value::Map(entries) = value else {
+            return false;
+        };
+        entries.iter().any(|(key, value)| {
+            key.strip_prefix('@')
+                .is_some_and(|name| name.rsplit(':').next() == Some("nil"))
+                && matches!(value.text().map(str::trim), Some("true" | "1"))
+        })
+    }
 
-        Ok(file)
+    pub fn parse_sale_or_empty_string<'de, D>(deserializer: D) -> Result<Option<Sale>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut value = Value::deserialize(deserializer)?;
+        if is_nil(&value) {
+            return Ok(None);
+        }
+        // Namespace declarations are not content, so `<Sale xmlns:xsi="..."/>` is empty.
+        if let Value::Map(entries) = &mut value {
+            entries.retain(|(key, _)| !key.starts_with("@xmlns"));
+        }
+        match value.text() {
+            Some("") => Ok(None),
+            // Documents from before `Sale` had variants hold a bare percentage.
+            Some(text) => Ok(Some(Sale::PercentOff(text.parse().map_err(|err| {
+                FieldError::InvalidNumber {
+                    element: "Sale".to_string(),
+                    value: text.to_string(),
+                    ty: Percent::type_name(),
+                    source: ParseNumberError::Decimal(err),
+                }
+                .raise::<D::Error>()
+            })?))),
+            None if value == Value::Unit => Ok(None),
+            // Formats with a number type, such as JSON, hold the legacy percentage as one.
+            None if matches!(value, Value::I64(_) | Value::U64(_) | Value::F64(_)) => value
+                .deserialize_into("Sale")
+                .map(|percent| Some(Sale::PercentOff(percent))),
+            None => value.deserialize_into("Sale").map(Some),
+        }
     }
 }
 
+pub mod catalog;
+pub mod conversion;
+pub mod csv;
+pub mod extra;
+pub mod flatten;
+pub mod json;
+pub mod json_schema;
+pub mod tagged;
+#[cfg(feature = "toml")]
+pub mod toml;
+pub mod validate;
+pub mod xml;
+pub mod xsd;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
 #[cfg(test)]
 pub mod tests {
-    use crate::newtype_variant_enum::types::Sale;
+    use crate::newtype_variant_enum::types::{
+        Amount, CurrencyCode, CurrencyEncoding, CurrencyNaming, Money, NoSale, Sale,
+    };
     use pretty_assertions::assert_eq;
-    use std::path::PathBuf;
+    use std::{error::Error, fs, path::PathBuf};
 
     use super::{
         Product,
+        extra::Extra,
         types::Currency,
-        xml::{from_xml_file, to_xml_file},
+        xml::{self, Indent, ReadOptions, WriteOptions, XmlError, from_xml_file, to_xml_file},
     };
 
+    /// Where a test writes its fixture `file_name`: under the system temp dir and
+    /// unique to this test run, so parallel runs never share one. Tests remove their
+    /// fixtures when done.
+    fn temp_path(file_name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "newtype_enum_variant-{}-{file_name}",
+            std::process::id()
+        ))
+    }
+
     #[test]
     fn export_only() {
-        let file_path = PathBuf::from("export.xml");
+        let file_path = temp_path("export.xml");
         let out = Product {
             name: "Fidget Spinner".to_string(),
-            price: Currency::Euros(3.5),
+            price: Currency::Euros(Amount::from_minor(350)),
             sale: None,
+            extra: Extra::default(),
         };
 
         to_xml_file(&file_path, &out).expect("should have written object to file");
+        fs::remove_file(&file_path).expect("should remove the fixture");
     }
 
     #[test]
@@ -154,8 +637,9 @@ pub mod tests {
         let file_path = PathBuf::from("import.xml");
         let exp = Product {
             name: "Fidget Spinner".to_string(),
-            price: Currency::Euros(3.5),
+            price: Currency::Euros(Amount::from_minor(350)),
             sale: None,
+            extra: Extra::default(),
         };
 
         let res = from_xml_file(&file_path).expect("should have read object into memory");
@@ -164,11 +648,12 @@ pub mod tests {
 
     #[test]
     fn both_export_and_import_without_rating() {
-        let file_path = PathBuf::from("test.xml");
+        let file_path = temp_path("test.xml");
         let obj = Product {
             name: "F-22 Raptor".to_string(),
-            price: Currency::Dollars(350000000.0),
+            price: Currency::Dollars(Amount::from_major(350_000_000)),
             sale: None,
+            extra: Extra::default(),
         };
 
         // Export
@@ -177,15 +662,17 @@ pub mod tests {
         // Import
         let res = from_xml_file(&file_path).expect("should have read object into memory");
         assert_eq!(res, obj, "imported object does not match original");
+        fs::remove_file(&file_path).expect("should remove the fixture");
     }
 
     #[test]
     fn both_export_and_import_with_rating() {
-        let file_path = PathBuf::from("test1.xml");
+        let file_path = temp_path("test1.xml");
         let obj = Product {
             name: "Scrub Daddy".to_string(),
-            price: Currency::Dollars(6.0),
-            sale: Some(Sale(25.5)),
+            price: Currency::Dollars(Amount::from_major(6)),
+            sale: Some(Sale::PercentOff(Amount::from_minor(2550))),
+            extra: Extra::default(),
         };
 
         // Export
@@ -194,5 +681,2008 @@ pub mod tests {
         // Import
         let res = from_xml_file(&file_path).expect("should have read object into memory");
         assert_eq!(res, obj, "imported object does not match original");
+        fs::remove_file(&file_path).expect("should remove the fixture");
+    }
+
+    fn import_str(file_name: &str, xml: &str) -> Result<Product, XmlError> {
+        let file_path = temp_path(file_name);
+        fs::write(&file_path, xml).expect("should have written fixture");
+        let res = from_xml_file(&file_path);
+        fs::remove_file(&file_path).expect("should remove the fixture");
+        res
+    }
+
+    #[test]
+    fn import_missing_file_is_io_error() {
+        let err = from_xml_file("does-not-exist.xml").unwrap_err();
+        assert!(matches!(err, XmlError::Io(_)), "{err:?}");
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn import_malformed_reports_position() {
+        let err = import_str(
+            "malformed.xml",
+            "<Product>\n<Name>Yo-yo</Name>\n<Euros>1.0</Dollars>\n</Product>",
+        )
+        .unwrap_err();
+        let XmlError::Syntax { position, line, .. } = err else {
+            panic!("expected a syntax error, got {err:?}");
+        };
+        assert_eq!((position, line), (39, 3));
+    }
+
+    #[test]
+    fn import_unknown_currency() {
+        let err = import_str(
+            "unknown_currency.xml",
+            "<Product><Name>Yo-yo</Name><Pesos>1.0</Pesos><Sale/></Product>",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnknownCurrency(ref key) if key == "Pesos"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn import_invalid_sale_number() {
+        let err = import_str(
+            "invalid_sale.xml",
+            "<Product><Name>Yo-yo</Name><Euros>1.0</Euros><Sale>lots</Sale></Product>",
+        )
+        .unwrap_err();
+        let XmlError::InvalidNumber { element, value, .. } = &err else {
+            panic!("expected an invalid number, got {err:?}");
+        };
+        assert_eq!((element.as_str(), value.as_str()), ("Sale", "lots"));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn import_missing_name() {
+        let err = import_str(
+            "missing_name.xml",
+            "<Product><Euros>1.0</Euros><Sale/></Product>",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, XmlError::MissingElement(ref name) if name == "Name"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn round_trip_in_memory() {
+        let obj = Product {
+            name: "Scrub Daddy".to_string(),
+            price: Currency::Euros(Amount::from_major(6)),
+            sale: Some(Sale::PercentOff(Amount::from_minor(2550))),
+            extra: Extra::default(),
+        };
+
+        let out = xml::to_string(&obj).expect("should have serialized object");
+        let res: Product = xml::from_reader(out.as_bytes()).expect("should have deserialized");
+        assert_eq!(res, obj, "imported object does not match original");
+    }
+
+    #[test]
+    fn round_trip_other_type() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Shelf {
+            aisle: u32,
+            label: String,
+        }
+        let obj = Shelf {
+            aisle: 7,
+            label: "Cleaning".to_string(),
+        };
+
+        let mut buf = Vec::new();
+        xml::to_writer(&mut buf, &obj).expect("should have serialized object");
+        let res: Shelf =
+            xml::from_str(std::str::from_utf8(&buf).unwrap()).expect("should have deserialized");
+        assert_eq!(res, obj);
+    }
+
+    #[test]
+    fn write_with_options() {
+        let obj = Product {
+            name: "Fidget Spinner".to_string(),
+            price: Currency::Euros(Amount::from_minor(350)),
+            sale: None,
+            extra: Extra::default(),
+        };
+        let options = WriteOptions {
+            root: "Item".to_string(),
+            declaration: true,
+            indent: Some(Indent { char: ' ', size: 2 }),
+            ..Default::default()
+        };
+
+        let out = xml::to_string_with(&obj, &options).expect("should have serialized object");
+        assert_eq!(
+            out,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <Item>\n  <Name>Fidget Spinner</Name>\n  <Euros>3.50</Euros>\n  <Sale/>\n</Item>"
+        );
+        let res: Product = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj);
+
+        // Output is always UTF-8, so the declaration may not claim anything else.
+        let options = WriteOptions {
+            encoding: Some("ISO-8859-1".to_string()),
+            ..options
+        };
+        let err = xml::to_string_with(&obj, &options).unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnsupportedEncoding(ref encoding) if encoding == "ISO-8859-1"),
+            "{err:?}"
+        );
+        let options = WriteOptions {
+            encoding: Some("utf-8".to_string()),
+            ..options
+        };
+        let out = xml::to_string_with(&obj, &options).expect("should have serialized object");
+        assert!(out.starts_with(r#"<?xml version="1.0" encoding="utf-8"?>"#));
+    }
+
+    #[test]
+    fn read_rejects_unexpected_root() {
+        let options = ReadOptions {
+            root: Some("Product".to_string()),
+            ..ReadOptions::default()
+        };
+        let input = "<DeviceTag><Name>Yo-yo</Name><Euros>1.0</Euros><Sale/></DeviceTag>";
+
+        let err = xml::from_str_with::<Product>(input, &options).unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnexpectedRoot { ref found, .. } if found == "DeviceTag"),
+            "{err:?}"
+        );
+        let input = input.replace("DeviceTag", "Product");
+        xml::from_str_with::<Product>(&input, &options).expect("should have deserialized");
+    }
+
+    #[test]
+    fn derived_enum_with_other_inner_types() {
+        use super::FlattenedNewtypeEnum;
+
+        // Reached through `crate` rather than `::newtype_enum_variant`.
+        #[derive(Debug, PartialEq, FlattenedNewtypeEnum)]
+        #[flattened(crate = "crate")]
+        enum Identifier {
+            Sku(String),
+            #[flattened(rename = "GTIN")]
+            Gtin(u64),
+        }
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Listing {
+            name: String,
+            #[serde(flatten)]
+            id: Identifier,
+        }
+
+        for obj in [
+            Listing {
+                name: "Scrub Daddy".to_string(),
+                id: Identifier::Sku("SD-001".to_string()),
+            },
+            Listing {
+                name: "F-22 Raptor".to_string(),
+                id: Identifier::Gtin(4006381333931),
+            },
+        ] {
+            let out = xml::to_string(&obj).expect("should have serialized object");
+            let res: Listing = xml::from_str(&out).expect("should have deserialized");
+            assert_eq!(res, obj, "imported object does not match original");
+        }
+
+        let err = xml::from_str::<Listing>("<Listing><Name>x</Name><GTIN>12a</GTIN></Listing>")
+            .unwrap_err();
+        assert!(
+            matches!(err, XmlError::InvalidNumber { ref element, .. } if element == "GTIN"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn derived_enum_with_structured_inner_type() {
+        use super::FlattenedNewtypeEnum;
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Size {
+            width: u32,
+            height: u32,
+            #[serde(rename = "@unit")]
+            unit: String,
+        }
+
+        #[derive(Debug, PartialEq, FlattenedNewtypeEnum)]
+        enum Packaging {
+            Boxed(Size),
+            Loose(bool),
+        }
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Parcel {
+            #[serde(flatten)]
+            packaging: Packaging,
+            weight: f64,
+        }
+
+        for obj in [
+            Parcel {
+                packaging: Packaging::Boxed(Size {
+                    width: 3,
+                    height: 4,
+                    unit: "cm".to_string(),
+                }),
+                weight: 0.5,
+            },
+            Parcel {
+                packaging: Packaging::Loose(true),
+                weight: 1.25,
+            },
+        ] {
+            let out = xml::to_string(&obj).expect("should have serialized object");
+            let res: Parcel = xml::from_str(&out).expect("should have deserialized");
+            assert_eq!(res, obj, "imported object does not match original");
+        }
+    }
+
+    #[test]
+    fn derived_enum_with_optional_and_repeated_inner_types() {
+        use super::FlattenedNewtypeEnum;
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Tags {
+            #[serde(rename = "Tag")]
+            tags: Vec<String>,
+        }
+
+        #[derive(Debug, PartialEq, FlattenedNewtypeEnum)]
+        enum Label {
+            Tagged(Tags),
+            Rating(Option<u8>),
+        }
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Entry {
+            name: String,
+            #[serde(flatten)]
+            label: Label,
+        }
+
+        for obj in [
+            Entry {
+                name: "Scrub Daddy".to_string(),
+                label: Label::Tagged(Tags {
+                    tags: vec!["kitchen".to_string(), "sponge".to_string()],
+                }),
+            },
+            Entry {
+                name: "Scrub Daddy".to_string(),
+                label: Label::Rating(Some(4)),
+            },
+            Entry {
+                name: "F-22 Raptor".to_string(),
+                label: Label::Rating(None),
+            },
+        ] {
+            let out = xml::to_string(&obj).expect("should have serialized object");
+            let res: Entry = xml::from_str(&out).expect("should have deserialized");
+            assert_eq!(res, obj, "imported object does not match original");
+        }
+    }
+
+    #[test]
+    fn amounts_round_trip_exactly() {
+        let obj = Product {
+            name: "Penny Candy".to_string(),
+            price: Currency::Dollars("350000000.01".parse().unwrap()),
+            sale: Some(Sale::PercentOff("0.1".parse().unwrap())),
+            extra: Extra::default(),
+        };
+
+        let out = xml::to_string(&obj).expect("should have serialized object");
+        assert!(
+            out.contains(
+                "<Dollars>350000000.01</Dollars><Sale><PercentOff>0.10</PercentOff></Sale>"
+            ),
+            "{out}"
+        );
+        let res: Product = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj, "imported object does not match original");
+        assert_eq!(
+            res.price,
+            Currency::Dollars(Amount::from_minor(35_000_000_001))
+        );
+
+        let most = i64::MAX / 100;
+        assert_eq!(
+            Amount::<2>::checked_from_major(most),
+            Some(Amount::from_minor(most * 100))
+        );
+        assert_eq!(Amount::<2>::checked_from_major(most + 1), None);
+        assert_eq!(Amount::<2>::checked_from_major(-most - 1), None);
+        assert_eq!(Amount::<19>::checked_from_major(1), None);
+        assert!(std::panic::catch_unwind(|| Amount::<2>::from_major(most + 1)).is_err());
+    }
+
+    #[test]
+    fn amounts_reject_excess_fraction_digits() {
+        let err = xml::from_str::<Product>(
+            "<Product><Name>Yo-yo</Name><Dollars>1.005</Dollars><Sale/></Product>",
+        )
+        .unwrap_err();
+        let XmlError::InvalidNumber { element, value, .. } = &err else {
+            panic!("expected an invalid number, got {err:?}");
+        };
+        assert_eq!((element.as_str(), value.as_str()), ("Dollars", "1.005"));
+        assert!(err.source().is_some());
+
+        let err = xml::from_str::<Product>(
+            "<Product><Name>Yo-yo</Name><Dollars>1.00</Dollars><Sale>NaN</Sale></Product>",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, XmlError::InvalidNumber { ref element, .. } if element == "Sale"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn currencies_by_legacy_name_or_iso_code() {
+        let usd = CurrencyCode::from_alpha("USD").unwrap();
+        assert_eq!((usd.numeric, usd.minor_units), (840, 2));
+        assert_eq!(CurrencyCode::from_numeric(48).unwrap().alpha, "BHD");
+
+        let res: Product =
+            xml::from_str("<Product><Name>Yo-yo</Name><USD>6.00</USD><Sale/></Product>")
+                .expect("should have deserialized");
+        assert_eq!(res.price, Currency::Dollars(Amount::from_major(6)));
+
+        let yen = CurrencyCode::from_alpha("JPY").unwrap();
+        let obj = Product {
+            name: "Yo-yo".to_string(),
+            price: Currency::from_minor(yen, 1200),
+            sale: None,
+            extra: Extra::default(),
+        };
+        let out = xml::to_string(&obj).expect("should have serialized object");
+        assert!(out.contains("<JPY>1200</JPY>"), "{out}");
+        let res: Product = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj, "imported object does not match original");
+
+        let err =
+            xml::from_str::<Product>("<Product><Name>Yo-yo</Name><JPY>1.5</JPY><Sale/></Product>")
+                .unwrap_err();
+        assert!(
+            matches!(err, XmlError::InvalidNumber { ref element, .. } if element == "JPY"),
+            "{err:?}"
+        );
+        let err =
+            xml::from_str::<Product>("<Product><Name>Yo-yo</Name><XYZ>1</XYZ><Sale/></Product>")
+                .unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnknownCurrency(ref key) if key == "XYZ"),
+            "{err:?}"
+        );
+
+        // `Other` built by hand with USD stands for `Dollars`, whatever the naming.
+        let other = Currency::Other(Money::from_minor(usd, 600));
+        assert_eq!(other, Currency::Dollars(Amount::from_major(6)));
+        assert_ne!(other, Currency::Euros(Amount::from_major(6)));
+        assert_eq!(other.element_name(), "Dollars");
+        let obj = Product {
+            name: "Yo-yo".to_string(),
+            price: other,
+            sale: Some(Sale::SalePrice(Currency::Other(Money::from_minor(
+                usd, 500,
+            )))),
+            extra: Extra::default(),
+        };
+        let out = xml::to_string(&obj).expect("should have serialized object");
+        assert!(
+            out.contains("<Dollars>6.00</Dollars><Sale><SalePrice><Dollars>5.00</Dollars>"),
+            "{out}"
+        );
+        assert_eq!(xml::from_str::<Product>(&out).unwrap(), obj);
+        let options = WriteOptions {
+            currency_naming: CurrencyNaming::Iso,
+            ..WriteOptions::default()
+        };
+        let out = xml::to_string_with(&obj, &options).expect("should have serialized object");
+        assert!(
+            out.contains("<USD>6.00</USD><Sale><SalePrice><USD>5.00</USD>"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn write_iso_currency_names() {
+        let obj = Product {
+            name: "Fidget Spinner".to_string(),
+            price: Currency::Euros(Amount::from_minor(350)),
+            sale: None,
+            extra: Extra::default(),
+        };
+        let options = WriteOptions {
+            currency_naming: CurrencyNaming::Iso,
+            ..Default::default()
+        };
+
+        let out = xml::to_string_with(&obj, &options).expect("should have serialized object");
+        assert!(out.contains("<EUR>3.50</EUR>"), "{out}");
+        let res: Product = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj, "imported object does not match original");
+        let out = xml::to_string(&obj).expect("should have serialized object");
+        assert!(out.contains("<Euros>3.50</Euros>"), "{out}");
+    }
+
+    #[test]
+    fn convert_between_currencies() {
+        use super::conversion::{
+            ConversionError, Rate, RoundingMode, RoundingRule, RoundingRules, StaticRates, convert,
+            convert_with,
+        };
+        use super::types::ParseAmountError;
+
+        let code = |alpha| CurrencyCode::from_alpha(alpha).unwrap();
+        let rates = StaticRates::new()
+            .with(code("USD"), code("EUR"), "0.9215".parse().unwrap())
+            .with(code("USD"), code("JPY"), Rate::new(14_950, 2))
+            .with(code("EUR"), code("CHF"), "0.9437".parse().unwrap());
+
+        let price = Currency::Dollars(Amount::from_major(6));
+        let euros = convert(&price, code("EUR"), &rates).expect("should have converted");
+        assert_eq!(euros, Currency::Euros(Amount::from_minor(553)));
+        let yen = convert(&price, code("JPY"), &rates).expect("should have converted");
+        assert_eq!(yen, Currency::from_minor(code("JPY"), 897));
+
+        let cash = RoundingRules::default().with(
+            code("CHF"),
+            RoundingRule {
+                mode: RoundingMode::HalfUp,
+                increment: 5,
+            },
+        );
+        let francs =
+            convert_with(&euros, code("CHF"), &rates, &cash).expect("should have converted");
+        assert_eq!(francs, Currency::from_minor(code("CHF"), 520));
+
+        assert_eq!(
+            "0.000000000000000001"
+                .parse::<Rate>()
+                .map(|rate| rate.to_string()),
+            Ok("0.000000000000000001".to_string())
+        );
+        assert_eq!(
+            "0.00000000000000000001".parse::<Rate>(),
+            Err(ParseAmountError::Overflow)
+        );
+
+        let err = convert(&euros, code("USD"), &rates).unwrap_err();
+        assert_eq!(
+            err,
+            ConversionError::MissingRate {
+                from: "EUR".to_string(),
+                to: "USD".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn load_rates_from_files() {
+        use super::conversion::{FileRates, Rate, RateFileError, RateProvider};
+
+        let code = |alpha| CurrencyCode::from_alpha(alpha).unwrap();
+        let load = |file_name, contents| {
+            let file_path = temp_path(file_name);
+            fs::write(&file_path, contents).expect("should have written fixture");
+            let res = FileRates::load(&file_path);
+            fs::remove_file(&file_path).expect("should remove the fixture");
+            res
+        };
+        let rates = load("rates.csv", "From,To,Rate\nUSD,EUR,0.92\n\nEUR,USD,1.087\n")
+            .expect("should have loaded rates");
+        assert_eq!(
+            rates.rate(code("EUR"), code("USD")),
+            Some(Rate::new(1087, 3))
+        );
+
+        let rates = load(
+            "rates.xml",
+            r#"<Rates><Rate from="USD" to="EUR">0.92</Rate><Rate from="USD" to="GBP">0.79</Rate></Rates>"#,
+        )
+        .expect("should have loaded rates");
+        assert_eq!(rates.rate(code("USD"), code("GBP")), Some(Rate::new(79, 2)));
+        assert_eq!(rates.rate(code("GBP"), code("USD")), None);
+
+        let err = load("rates_bad.csv", "USD,EUR,0.92\nUSD,ABC,1\n").unwrap_err();
+        assert!(
+            matches!(err, RateFileError::Invalid { line: 2, .. }),
+            "{err:?}"
+        );
+
+        let rates = load(
+            "rates_commented.csv",
+            "# Rates as of 2026-10-18\n\nFrom,To,Rate\nUSD,EUR,0.92\n",
+        )
+        .expect("should have skipped the comment before the header");
+        assert_eq!(rates.rate(code("USD"), code("EUR")), Some(Rate::new(92, 2)));
+        let err = load("rates_late_header.csv", "USD,EUR,0.92\nFrom,To,Rate\n").unwrap_err();
+        assert!(
+            matches!(err, RateFileError::Invalid { line: 2, .. }),
+            "{err:?}"
+        );
+
+        for contents in ["USD,EUR,0\n", "USD,EUR,0.00\n", "USD,EUR,-0.92\n"] {
+            let err = load("rates_sign.csv", contents).unwrap_err();
+            assert!(
+                matches!(err, RateFileError::Invalid { line: 1, ref message } if message.contains("not positive")),
+                "{err:?}"
+            );
+        }
+        let err = load(
+            "rates_sign.xml",
+            r#"<Rates><Rate from="USD" to="EUR">-1</Rate></Rates>"#,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, RateFileError::Invalid { line: 1, .. }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn sale_variants_round_trip() {
+        for sale in [
+            Sale::PercentOff(Amount::from_minor(2550)),
+            Sale::AmountOff(Currency::Dollars(Amount::from_minor(150))),
+            Sale::SalePrice(Currency::Dollars(Amount::from_major(5))),
+        ] {
+            let obj = Product {
+                name: "Scrub Daddy".to_string(),
+                price: Currency::Dollars(Amount::from_major(6)),
+                sale: Some(sale),
+                extra: Extra::default(),
+            };
+            let out = xml::to_string(&obj).expect("should have serialized object");
+            let res: Product = xml::from_str(&out).expect("should have deserialized");
+            assert_eq!(res, obj, "imported object does not match original");
+        }
+
+        let out = "<Product><Name>Yo-yo</Name><Dollars>6</Dollars><Sale>25.5</Sale></Product>";
+        let res: Product = xml::from_str(out).expect("should have deserialized");
+        assert_eq!(res.sale, Some(Sale::PercentOff(Amount::from_minor(2550))));
+    }
+
+    #[test]
+    fn effective_price_applies_sale() {
+        use super::types::SaleError;
+
+        let product = |sale| Product {
+            name: "Scrub Daddy".to_string(),
+            price: Currency::Dollars(Amount::from_minor(699)),
+            sale,
+            extra: Extra::default(),
+        };
+        let dollars = |minor| Currency::Dollars(Amount::from_minor(minor));
+
+        assert_eq!(product(None).effective_price(), Ok(dollars(699)));
+        assert_eq!(
+            product(Some(Sale::PercentOff(Amount::from_major(25)))).effective_price(),
+            Ok(dollars(524))
+        );
+        assert_eq!(
+            product(Some(Sale::AmountOff(dollars(199)))).effective_price(),
+            Ok(dollars(500))
+        );
+        assert_eq!(
+            product(Some(Sale::SalePrice(dollars(450)))).effective_price(),
+            Ok(dollars(450))
+        );
+
+        let err = product(Some(Sale::PercentOff(Amount::from_major(120))))
+            .effective_price()
+            .unwrap_err();
+        assert!(
+            matches!(err, SaleError::DiscountExceedsPrice { .. }),
+            "{err:?}"
+        );
+        let err = product(Some(Sale::AmountOff(dollars(700))))
+            .effective_price()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "discount of 7.00 USD exceeds the price of 6.99 USD"
+        );
+        let err = product(Some(Sale::SalePrice(Currency::Euros(Amount::from_major(
+            5,
+        )))))
+        .effective_price()
+        .unwrap_err();
+        assert!(matches!(err, SaleError::CurrencyMismatch { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn validate_reports_every_violation() {
+        use super::validate::{Validate, Violation};
+
+        let obj = Product {
+            name: "  ".to_string(),
+            price: Currency::Dollars(Amount::from_minor(-100)),
+            sale: Some(Sale::PercentOff(Amount::from_major(150))),
+            extra: Extra::default(),
+        };
+        let errors = obj.validate().unwrap_err();
+        let paths: Vec<_> = errors.0.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, ["name", "price", "sale.percent_off"]);
+        assert_eq!(
+            errors.to_string(),
+            "name: must not be empty; price: -1.00 USD must not be negative; \
+             sale.percent_off: 150.00% exceeds 100%"
+        );
+
+        let obj = Product {
+            name: "Yo-yo".to_string(),
+            price: Currency::Dollars(Amount::from_major(6)),
+            sale: Some(Sale::AmountOff(Currency::Euros(Amount::from_major(1)))),
+            extra: Extra::default(),
+        };
+        assert_eq!(
+            obj.validate().unwrap_err().0,
+            [Violation {
+                path: "sale".to_string(),
+                message: "sale in EUR cannot apply to a price in USD".to_string(),
+            }]
+        );
+
+        let obj = Product {
+            name: "Yo-yo".to_string(),
+            price: Currency::Dollars(Amount::from_major(6)),
+            sale: Some(Sale::SalePrice(Currency::Dollars(Amount::from_major(5)))),
+            extra: Extra::default(),
+        };
+        assert_eq!(obj.validate(), Ok(()));
+    }
+
+    #[test]
+    fn import_validated_rejects_invalid_product() {
+        let file_path = temp_path("validated.xml");
+        fs::write(
+            &file_path,
+            "<Product><Name></Name><Dollars>-6</Dollars><Sale/></Product>",
+        )
+        .expect("should have written fixture");
+
+        let res = from_xml_file(&file_path).expect("should parse without validation");
+        assert_eq!(res.price, Currency::Dollars(Amount::from_major(-6)));
+
+        let err = xml::from_xml_file_validated(&file_path).unwrap_err();
+        let XmlError::Invalid(errors) = &err else {
+            panic!("expected a validation error, got {err:?}");
+        };
+        assert_eq!(errors.0.len(), 2);
+        assert!(err.source().is_some());
+        fs::remove_file(&file_path).expect("should remove the fixture");
+
+        let product = xml::from_xml_file_validated("import.xml").expect("fixture is valid");
+        assert_eq!(product.name, "Fidget Spinner");
+    }
+
+    #[test]
+    fn catalog_round_trip() {
+        use super::catalog::Catalog;
+
+        let catalog = Catalog {
+            products: vec![
+                Product {
+                    name: "Yo-yo".to_string(),
+                    price: Currency::Dollars(Amount::from_major(6)),
+                    sale: Some(Sale::PercentOff(Amount::from_major(10))),
+                    extra: Extra::default(),
+                },
+                Product {
+                    name: "Kendama".to_string(),
+                    price: Currency::from_minor(CurrencyCode::from_alpha("JPY").unwrap(), 1200),
+                    sale: None,
+                    extra: Extra::default(),
+                },
+            ],
+        };
+        let options = WriteOptions {
+            root: "Catalog".to_string(),
+            ..WriteOptions::default()
+        };
+        let out = xml::to_string_with(&catalog, &options).expect("should have serialized");
+        assert!(
+            out.starts_with("<Catalog><Product><Name>Yo-yo</Name>"),
+            "{out}"
+        );
+        let res: Catalog = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(res, catalog);
+    }
+
+    #[test]
+    fn catalog_reader_continues_past_bad_products() {
+        use super::catalog::CatalogReader;
+
+        let input = "<?xml version=\"1.0\"?>
+<Catalog>
+  <Product><Name>Yo-yo</Name><Dollars>6</Dollars><Sale/></Product>
+  <Product>
+    <Name>Kendama</Name><Doubloons>3</Doubloons><Sale/>
+  </Product>
+  <Supplier><Name>Acme</Name></Supplier>
+  <Product><Name>Top</Name><Euros>2.50</Euros><Sale>10</Sale></Product>
+  <Product/>
+</Catalog>";
+        let results: Vec<_> = CatalogReader::new(input.as_bytes()).collect();
+        assert_eq!(results.len(), 4);
+
+        let names: Vec<_> = results
+            .iter()
+            .filter_map(|res| res.as_ref().ok())
+            .map(|product| product.name.as_str())
+            .collect();
+        assert_eq!(names, ["Yo-yo", "Top"]);
+
+        let err = results[1].as_ref().unwrap_err();
+        assert_eq!((err.index, err.line), (1, 4));
+        assert_eq!(&input[err.position as usize..][..9], "<Product>");
+        assert!(
+            matches!(&err.source, XmlError::UnknownCurrency(name) if name == "Doubloons"),
+            "{err:?}"
+        );
+        let err = results[3].as_ref().unwrap_err();
+        assert_eq!((err.index, err.line), (3, 9));
+        assert!(matches!(err.source, XmlError::MissingElement(_)), "{err:?}");
+    }
+
+    #[test]
+    fn catalog_reader_stops_at_malformed_markup() {
+        use super::catalog::CatalogReader;
+
+        let input = "<Catalog>
+  <Product><Name>Yo-yo</Name><Dollars>6</Dollars><Sale/></Product>
+  <Product><Name>Top</Euros></Product>
+  <Product><Name>Kendama</Name><Dollars>6</Dollars><Sale/></Product>
+</Catalog>";
+        let mut reader = CatalogReader::new(input.as_bytes());
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(matches!(err.source, XmlError::Syntax { .. }), "{err:?}");
+        assert!(reader.next().is_none());
+
+        let err = CatalogReader::new("<Product/>".as_bytes())
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            matches!(err.source, XmlError::UnexpectedRoot { .. }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn catalog_writer_replaces_file_on_finish() {
+        use super::catalog::{CatalogReader, CatalogWriteOptions, CatalogWriter};
+
+        let file_path = temp_path("catalog.xml");
+        let temp_files = || -> Vec<_> {
+            let prefix = file_path.file_name().unwrap().to_str().unwrap();
+            fs::read_dir(file_path.parent().unwrap())
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    let name = path.file_name().unwrap().to_str().unwrap();
+                    name.starts_with(prefix) && name.ends_with(".tmp")
+                })
+                .collect()
+        };
+        fs::write(&file_path, "<Catalog/>").expect("should have written fixture");
+
+        let products: Vec<_> = (1..=3)
+            .map(|i| Product {
+                name: format!("Yo-yo #{i}"),
+                price: Currency::Dollars(Amount::from_major(i)),
+                sale: None,
+                extra: Extra::default(),
+            })
+            .collect();
+        let options = CatalogWriteOptions {
+            flush_every: 2,
+            ..CatalogWriteOptions::default()
+        };
+        let mut writer = CatalogWriter::create_with(&file_path, &options).expect("should create");
+        for product in &products {
+            writer.write(product).expect("should write product");
+        }
+        assert_eq!(writer.len(), 3);
+        // Two products were flushed, but the destination still holds the old catalog.
+        let [temp_file] = &temp_files()[..] else {
+            panic!("expected one temporary file");
+        };
+        assert!(fs::read_to_string(temp_file).unwrap().contains("Yo-yo #2"));
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "<Catalog/>");
+        writer.finish().expect("should finish");
+        assert!(temp_files().is_empty());
+
+        let res: Vec<_> = CatalogReader::open(&file_path)
+            .expect("should open catalog")
+            .collect::<Result<_, _>>()
+            .expect("should read every product");
+        assert_eq!(res, products);
+
+        let mut writer = CatalogWriter::create(&file_path).expect("should create");
+        writer.write(&products[0]).expect("should write product");
+        drop(writer);
+        assert!(temp_files().is_empty());
+        assert_eq!(CatalogReader::open(&file_path).unwrap().count(), 3);
+
+        // Writers racing for one destination keep apart, and the last to finish wins whole.
+        let mut first = CatalogWriter::create(&file_path).expect("should create");
+        let mut second = CatalogWriter::create(&file_path).expect("should create");
+        assert_eq!(temp_files().len(), 2);
+        for product in &products {
+            first.write(product).expect("should write product");
+        }
+        second.write(&products[0]).expect("should write product");
+        first.finish().expect("should finish");
+        second.finish().expect("should finish");
+        assert!(temp_files().is_empty());
+        let res: Vec<_> = CatalogReader::open(&file_path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(res, products[..1]);
+        fs::remove_file(&file_path).expect("should remove the fixture");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        use super::json::{self, JsonError};
+
+        let input = r#"{"Name": "Scrub Daddy", "Dollars": 6.0, "Sale": 25.5}"#;
+        let res: Product = json::from_json_str(input).expect("should have deserialized");
+        let obj = Product {
+            name: "Scrub Daddy".to_string(),
+            price: Currency::Dollars(Amount::from_major(6)),
+            sale: Some(Sale::PercentOff(Amount::from_minor(2550))),
+            extra: Extra::default(),
+        };
+        assert_eq!(res, obj);
+
+        // A percentage off keeps the requested bare shape; other kinds need their key.
+        let out = json::to_json_string(&obj).expect("should have serialized");
+        assert_eq!(out, r#"{"Name":"Scrub Daddy","Dollars":6.0,"Sale":25.5}"#);
+        let res: Product = json::from_json_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj);
+        let obj = Product {
+            sale: Some(Sale::AmountOff(Currency::Dollars(Amount::from_minor(150)))),
+            ..obj
+        };
+        let out = json::to_json_string(&obj).expect("should have serialized");
+        assert_eq!(
+            out,
+            r#"{"Name":"Scrub Daddy","Dollars":6.0,"Sale":{"AmountOff":{"Dollars":1.5}}}"#
+        );
+        let res: Product = json::from_json_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj);
+
+        // The same `Product` written as XML keeps its decimal text.
+        let out = xml::to_string(&obj).expect("should have serialized");
+        assert!(out.contains("<Dollars>6.00</Dollars>"), "{out}");
+
+        let file_path = temp_path("round_trip.json");
+        let obj = Product {
+            name: "Kendama".to_string(),
+            price: Currency::from_minor(CurrencyCode::from_alpha("JPY").unwrap(), 1200),
+            sale: None,
+            extra: Extra::default(),
+        };
+        json::to_json_file(&file_path, &obj).expect("should have written object to file");
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            r#"{"Name":"Kendama","JPY":1200.0,"Sale":null}"#
+        );
+        let res = json::from_json_file(&file_path).expect("should have read object");
+        assert_eq!(res, obj);
+        fs::remove_file(&file_path).unwrap();
+
+        // Past 2^53 minor units an `f64` is no longer exact, so the decimal text is kept.
+        let kendama = |price| Product {
+            name: "Kendama".to_string(),
+            price,
+            sale: None,
+            extra: Extra::default(),
+        };
+        let jpy = CurrencyCode::from_alpha("JPY").unwrap();
+        for (price, expected) in [
+            (
+                Currency::from_minor(jpy, 1 << 53),
+                r#""JPY":9007199254740992.0"#,
+            ),
+            (
+                Currency::from_minor(jpy, (1 << 53) + 1),
+                r#""JPY":"9007199254740993""#,
+            ),
+            (
+                Currency::Dollars(Amount::from_minor(9_007_199_254_740_993)),
+                r#""Dollars":"90071992547409.93""#,
+            ),
+        ] {
+            let obj = kendama(price);
+            let out = json::to_json_string(&obj).expect("should have serialized");
+            assert!(out.contains(expected), "{out}");
+            let res: Product = json::from_json_str(&out).expect("should have deserialized");
+            assert_eq!(res, obj);
+        }
+
+        let err =
+            json::from_json_str::<Product>(r#"{"Name": "Yo-yo", "Doubloons": 3, "Sale": null}"#)
+                .unwrap_err();
+        assert!(
+            matches!(&err, JsonError::UnknownCurrency(name) if name == "Doubloons"),
+            "{err:?}"
+        );
+        let err =
+            json::from_json_str::<Product>(r#"{"Name": "Yo-yo", "Dollars": 6.001, "Sale": null}"#)
+                .unwrap_err();
+        assert!(
+            matches!(&err, JsonError::InvalidNumber { field, value, .. } if field == "Dollars" && value == "6.001"),
+            "{err:?}"
+        );
+        let err = json::from_json_str::<Product>(r#"{"Name": "Yo-yo", "#).unwrap_err();
+        assert!(matches!(err, JsonError::Syntax { line: 1, .. }), "{err:?}");
+        let err =
+            json::from_json_str::<Product>(r#"{"Name": "Yo-yo", "Dollars": 6, "Name": "Top"}"#)
+                .unwrap_err();
+        assert!(
+            matches!(&err, JsonError::DuplicateField { second, .. } if second == "Name"),
+            "{err:?}"
+        );
+        assert_eq!(err.to_string(), r#"duplicate field "Name""#);
+    }
+
+    #[cfg(any(feature = "toml", feature = "yaml"))]
+    fn fixture_products() -> [Product; 2] {
+        [
+            Product {
+                name: "Yo-yo".to_string(),
+                price: Currency::Dollars(Amount::from_minor(650)),
+                sale: None,
+                extra: Extra::default(),
+            },
+            Product {
+                name: "Kendama".to_string(),
+                price: Currency::Euros(Amount::from_major(12)),
+                sale: Some(Sale::PercentOff(Amount::from_minor(2550))),
+                extra: Extra::default(),
+            },
+        ]
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_round_trip() {
+        use super::toml::{TomlError, from_toml_str, to_toml_string};
+
+        let [without_sale, with_sale] = fixture_products();
+        let out = to_toml_string(&without_sale).expect("should have serialized");
+        assert_eq!(out, "Name = \"Yo-yo\"\nDollars = 6.5\n");
+        assert_eq!(from_toml_str::<Product>(&out).unwrap(), without_sale);
+
+        let out = to_toml_string(&with_sale).expect("should have serialized");
+        assert_eq!(
+            out,
+            "Name = \"Kendama\"\nEuros = 12.0\n\n[Sale]\nPercentOff = 25.5\n"
+        );
+        assert_eq!(from_toml_str::<Product>(&out).unwrap(), with_sale);
+
+        let err = from_toml_str::<Product>("Name = \"Yo-yo\"\nDoubloons = 3\n").unwrap_err();
+        assert!(
+            matches!(&err, TomlError::UnknownCurrency(key) if key == "Doubloons"),
+            "{err:?}"
+        );
+        let err = from_toml_str::<Product>("Dollars = 3\n").unwrap_err();
+        assert!(
+            matches!(&err, TomlError::MissingKey(key) if key == "Name"),
+            "{err:?}"
+        );
+        let err = from_toml_str::<Product>("Name = \"Yo-yo\"\nDollars = \n").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                TomlError::Syntax {
+                    line: 2,
+                    column: 11,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_round_trip() {
+        use super::yaml::{YamlError, from_yaml_str, to_yaml_string, to_yaml_writer};
+
+        let [without_sale, with_sale] = fixture_products();
+        let out = to_yaml_string(&without_sale).expect("should have serialized");
+        assert_eq!(out, "Name: Yo-yo\nDollars: 6.5\nSale: null\n");
+        assert_eq!(from_yaml_str::<Product>(&out).unwrap(), without_sale);
+        let res: Product = from_yaml_str("Name: Yo-yo\nDollars: 6.50\n").unwrap();
+        assert_eq!(res, without_sale);
+
+        let out = to_yaml_string(&with_sale).expect("should have serialized");
+        assert_eq!(
+            out,
+            "Name: Kendama\nEuros: 12.0\nSale:\n  PercentOff: 25.5\n"
+        );
+        assert_eq!(from_yaml_str::<Product>(&out).unwrap(), with_sale);
+
+        let err = from_yaml_str::<Product>("Name: Yo-yo\nDollars: 6.505\n").unwrap_err();
+        assert!(
+            matches!(&err, YamlError::InvalidNumber { key, .. } if key == "Dollars"),
+            "{err:?}"
+        );
+        let err = from_yaml_str::<Product>("Name: [Yo-yo\nDollars: 6.5\n").unwrap_err();
+        assert!(matches!(err, YamlError::Syntax { .. }), "{err:?}");
+
+        let err = to_yaml_writer(&mut [0; 4][..], &with_sale).unwrap_err();
+        assert!(matches!(err, YamlError::Io(_)), "{err:?}");
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        use super::csv::{self, CsvError};
+
+        let products = vec![
+            Product {
+                name: "Yo-yo, deluxe".to_string(),
+                price: Currency::Dollars(Amount::from_major(6)),
+                sale: Some(Sale::PercentOff(Amount::from_minor(2550))),
+                extra: Extra::default(),
+            },
+            Product {
+                name: "Kendama".to_string(),
+                price: Currency::from_minor(CurrencyCode::from_alpha("JPY").unwrap(), 1200),
+                sale: Some(Sale::SalePrice(Currency::from_minor(
+                    CurrencyCode::from_alpha("JPY").unwrap(),
+                    1000,
+                ))),
+                extra: Extra::default(),
+            },
+            Product {
+                name: "Top".to_string(),
+                price: Currency::Euros(Amount::from_minor(250)),
+                sale: None,
+                extra: Extra::default(),
+            },
+        ];
+        let out = csv::to_csv_string(&products).expect("should have serialized");
+        assert_eq!(
+            out,
+            "Name,Currency,Amount,Sale\n\
+             \"Yo-yo, deluxe\",Dollars,6.00,25.50\n\
+             Kendama,JPY,1200,SalePrice 1000 JPY\n\
+             Top,Euros,2.50,\n"
+        );
+        assert_eq!(csv::from_csv_str(&out).unwrap(), products);
+
+        // Columns are matched by header, ISO codes name the legacy variants too.
+        let res = csv::from_csv_str("Amount,Name,Currency\n6.5,Yo-yo,USD\n").unwrap();
+        assert_eq!(res[0].price, Currency::Dollars(Amount::from_minor(650)));
+        assert_eq!(res[0].sale, None);
+
+        let input = "Name,Currency,Amount,Sale\nYo-yo,Dollars,6,\nTop,Doubloons,3,\n";
+        let err = csv::from_csv_str(input).unwrap_err();
+        assert!(
+            matches!(&err, CsvError::InvalidCell { row: 3, column, .. } if column == "Currency"),
+            "{err:?}"
+        );
+        let input = "Name,Currency,Amount,Sale\nYo-yo,Dollars,6.001,\n";
+        let err = csv::from_csv_str(input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "row 2, column \"Amount\": invalid number `6.001` in <Dollars>, expected decimal(2): \
+             3 fraction digits given but at most 2 allowed"
+        );
+        let input = "Name,Currency,Amount,Sale\nYo-yo,Dollars,6,BOGO\n";
+        let err = csv::from_csv_str(input).unwrap_err();
+        assert!(
+            matches!(&err, CsvError::InvalidCell { row: 2, column, .. } if column == "Sale"),
+            "{err:?}"
+        );
+        let err = csv::from_csv_str("Name,Amount\n").unwrap_err();
+        assert!(matches!(err, CsvError::MissingColumn(_)), "{err:?}");
+
+        // Sale cells may be padded, spaced out or carry a percent sign.
+        let sale = |cell: &str| {
+            let input = format!("Name,Currency,Amount,Sale\nYo-yo,Dollars,6,\"{cell}\"\n");
+            csv::from_csv_str(&input).map(|products| products[0].sale.clone())
+        };
+        let percent = Some(Sale::PercentOff(Amount::from_minor(2550)));
+        for cell in ["25.50", "25.5%", "25.50 %", "  25.5  ", "\t25.5 %"] {
+            assert_eq!(sale(cell).unwrap(), percent, "{cell:?}");
+        }
+        let off = Some(Sale::AmountOff(Currency::Dollars(Amount::from_minor(150))));
+        for cell in ["AmountOff 1.50 USD", " AmountOff  1.5\tDollars "] {
+            assert_eq!(sale(cell).unwrap(), off, "{cell:?}");
+        }
+        for (cell, expected) in [
+            ("25.5 off", "invalid number `25.5 off` in <Sale>"),
+            ("%25", "invalid number `%25` in <Sale>"),
+            ("25,5", "invalid number `25,5` in <Sale>"),
+            ("Discount 1.50 USD", "unknown Sale element `Discount`"),
+            ("AmountOff", "missing"),
+            ("AmountOff 1.50", "missing"),
+            (
+                "SalePrice 1.50 Doubloons",
+                "unknown Currency element `Doubloons`",
+            ),
+            ("SalePrice 1.5.0 USD", "invalid number `1.5.0` in <USD>"),
+        ] {
+            let err = sale(cell).unwrap_err();
+            assert!(
+                matches!(&err, CsvError::InvalidCell { row: 2, column, .. } if column == "Sale"),
+                "{cell:?}: {err:?}"
+            );
+            assert!(err.to_string().contains(expected), "{cell:?}: {err}");
+        }
+
+        // Extra elements have no column, so leaving them out takes a flag.
+        let mut products = products;
+        products[1]
+            .extra
+            .push("Color", super::flatten::Value::Str("red".to_string()));
+        let err = csv::to_csv_string(&products).unwrap_err();
+        assert!(
+            matches!(&err, CsvError::UnwrittenExtra { row: 3, elements } if elements == &["Color"]),
+            "{err:?}"
+        );
+        let options = csv::WriteOptions {
+            drop_extra: true,
+            ..csv::WriteOptions::default()
+        };
+        let out = csv::to_csv_string_with(&products, &options).expect("should have serialized");
+        assert!(!out.contains("red"), "{out}");
+    }
+
+    /// Validates `document` against `schema` with xmllint, returning its complaints.
+    ///
+    /// Fails rather than skips when xmllint (from libxml2) is not installed, so a
+    /// missing tool never passes for a valid schema.
+    fn xmllint(schema: &str, document: &str) -> Result<(), String> {
+        use std::{
+            process::Command,
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        // Tests validating at the same time each get their own files.
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let run = RUNS.fetch_add(1, Ordering::Relaxed);
+        let schema_path = temp_path(&format!("schema_{run}.xsd"));
+        let document_path = temp_path(&format!("document_{run}.xml"));
+        fs::write(&schema_path, schema).expect("should have written schema");
+        fs::write(&document_path, document).expect("should have written document");
+        let output = Command::new("xmllint")
+            .arg("--noout")
+            .arg("--schema")
+            .args([&schema_path, &document_path])
+            .output();
+        fs::remove_file(&schema_path).expect("should remove the fixture");
+        fs::remove_file(&document_path).expect("should remove the fixture");
+        let output = output.expect("xmllint from libxml2 is needed to validate against the XSD");
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).into_owned())
+        }
+    }
+
+    #[test]
+    fn serialized_documents_validate_against_xsd() {
+        use super::{catalog::Catalog, flatten::Value};
+
+        let schema = super::xsd::schema(None);
+        assert!(schema.contains(r#"<xs:group name="Currency">"#), "{schema}");
+        assert!(schema.contains(r#"<xs:element name="Dollars" type="Decimal2"/>"#));
+        assert!(schema.contains(r#"<xs:element name="Euros" type="Decimal2"/>"#));
+        assert!(
+            schema
+                .contains(r#"<xs:element name="Sale" type="Sale" minOccurs="0" nillable="true"/>"#)
+        );
+        let product = |price, sale| Product {
+            name: "Yo-yo".to_string(),
+            price,
+            sale,
+            extra: Extra::default(),
+        };
+        let jpy = CurrencyCode::from_alpha("JPY").unwrap();
+        let products = vec![
+            product(Currency::Dollars(Amount::from_major(6)), None),
+            product(
+                Currency::Euros(Amount::from_minor(250)),
+                Some(Sale::PercentOff(Amount::from_minor(2550))),
+            ),
+            product(
+                Currency::from_minor(jpy, 1200),
+                Some(Sale::SalePrice(Currency::from_minor(jpy, 1000))),
+            ),
+            product(
+                Currency::Dollars(Amount::from_major(6)),
+                Some(Sale::AmountOff(Currency::Dollars(Amount::from_minor(150)))),
+            ),
+        ];
+        let mut documents = Vec::new();
+        for naming in [CurrencyNaming::Legacy, CurrencyNaming::Iso] {
+            let options = WriteOptions {
+                declaration: true,
+                currency_naming: naming,
+                ..WriteOptions::default()
+            };
+            for obj in &products {
+                documents.push(xml::to_string_with(obj, &options).unwrap());
+            }
+            let options = WriteOptions {
+                root: "Catalog".to_string(),
+                ..options
+            };
+            let catalog = Catalog { products: vec![] };
+            documents.push(xml::to_string_with(&catalog, &options).unwrap());
+        }
+        for no_sale in [NoSale::Omit, NoSale::Nil] {
+            let options = WriteOptions {
+                no_sale,
+                ..WriteOptions::default()
+            };
+            documents.push(xml::to_string_with(&products[0], &options).unwrap());
+        }
+        for naming in [CurrencyNaming::Legacy, CurrencyNaming::Iso] {
+            let options = WriteOptions {
+                currency_naming: naming,
+                currency_encoding: CurrencyEncoding::Attribute,
+                ..WriteOptions::default()
+            };
+            for obj in &products {
+                let document = xml::to_string_with(obj, &options).unwrap();
+                assert!(document.contains("<Price currency="), "{document}");
+                documents.push(document);
+            }
+        }
+        documents.push(
+            "<Product><Name>Yo-yo</Name><Price currency=\"Dollars\">6.5</Price></Product>"
+                .to_string(),
+        );
+        let input = "<Product xmlns:x=\"urn:other\"><Name>Yo-yo</Name><Dollars>6</Dollars>\
+                     <x:Color x:shade=\"dark\">red</x:Color><x:Sku>YY-1</x:Sku></Product>";
+        let options = ReadOptions {
+            namespace: Some("urn:example:products".to_string()),
+            ..ReadOptions::default()
+        };
+        let with_extra: Product = xml::from_str_with(input, &options).unwrap();
+        assert!(with_extra.extra.get("x:Color").is_some(), "{with_extra:?}");
+        documents.push(xml::to_string(&with_extra).unwrap());
+        let catalog = Catalog { products };
+        documents.push(
+            xml::to_string_with(
+                &catalog,
+                &WriteOptions {
+                    root: "Catalog".to_string(),
+                    ..WriteOptions::default()
+                },
+            )
+            .unwrap(),
+        );
+
+        for document in &documents {
+            if let Err(complaints) = xmllint(&schema, document) {
+                panic!("{document}\n{complaints}");
+            }
+        }
+
+        // A document with neither currency is rejected.
+        xmllint(&schema, "<Product><Name>Yo-yo</Name><Sale/></Product>").unwrap_err();
+        // Documents in a namespace validate against a schema targeting it, and only
+        // against that one.
+        const PARTNER: &str = "urn:partner:catalog:v2";
+        let namespaced = super::xsd::schema(Some(PARTNER));
+        assert!(
+            namespaced.contains(&format!(
+                r#"targetNamespace="{PARTNER}" xmlns="{PARTNER}" elementFormDefault="qualified""#
+            )),
+            "{namespaced}"
+        );
+        for prefix in [None, Some("p".to_string())] {
+            let options = WriteOptions {
+                namespace: Some(xml::Namespace {
+                    uri: PARTNER.to_string(),
+                    prefix,
+                }),
+                ..WriteOptions::default()
+            };
+            for obj in &catalog.products {
+                let document = xml::to_string_with(obj, &options).unwrap();
+                if let Err(complaints) = xmllint(&namespaced, &document) {
+                    panic!("{document}\n{complaints}");
+                }
+                xmllint(&schema, &document).unwrap_err();
+            }
+            let options = WriteOptions {
+                root: "Catalog".to_string(),
+                ..options
+            };
+            let document = xml::to_string_with(&catalog, &options).unwrap();
+            if let Err(complaints) = xmllint(&namespaced, &document) {
+                panic!("{document}\n{complaints}");
+            }
+        }
+        xmllint(&namespaced, &documents[0]).unwrap_err();
+
+        // Unqualified extras do not validate, see the `xsd` module docs.
+        let mut with_extra = Product {
+            extra: Extra::default(),
+            ..with_extra
+        };
+        with_extra
+            .extra
+            .push("Color", Value::Str("red".to_string()));
+        xmllint(&schema, &xml::to_string(&with_extra).unwrap()).unwrap_err();
+        // `<Price>` needs its currency, and only stands for a product's price.
+        xmllint(
+            &schema,
+            "<Product><Name>Yo-yo</Name><Price>6</Price></Product>",
+        )
+        .unwrap_err();
+        xmllint(
+            &schema,
+            "<Product><Name>Yo-yo</Name><Dollars>6</Dollars>\
+             <Sale><AmountOff><Price currency=\"USD\">1</Price></AmountOff></Sale></Product>",
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn json_documents_match_json_schema() {
+        use super::{
+            flatten, json,
+            json_schema::{self, SchemaOptions},
+        };
+        use serde_json::{Value, json};
+
+        let schema = json_schema::product_schema();
+        let validator = jsonschema::validator_for(&schema).expect("schema should compile");
+        let jpy = CurrencyCode::from_alpha("JPY").unwrap();
+        let products = [
+            Product {
+                name: "Yo-yo".to_string(),
+                price: Currency::Dollars(Amount::from_major(6)),
+                sale: None,
+                extra: Extra::default(),
+            },
+            Product {
+                name: "Top".to_string(),
+                price: Currency::Euros(Amount::from_minor(250)),
+                sale: Some(Sale::PercentOff(Amount::from_minor(2550))),
+                extra: Extra::default(),
+            },
+            Product {
+                name: "Kendama".to_string(),
+                price: Currency::from_minor(jpy, 1200),
+                sale: Some(Sale::AmountOff(Currency::from_minor(jpy, 200))),
+                extra: Extra::default(),
+            },
+        ];
+        for naming in [CurrencyNaming::Legacy, CurrencyNaming::Iso] {
+            let options = json::WriteOptions {
+                currency_naming: naming,
+                ..json::WriteOptions::default()
+            };
+            for product in &products {
+                let out = json::to_json_string_with(product, &options).unwrap();
+                let document: Value = serde_json::from_str(&out).unwrap();
+                assert!(validator.is_valid(&document), "{document}");
+            }
+        }
+
+        // Documents are accepted by each schema exactly when they deserialize, read
+        // leniently or strictly, and what they deserialize to is written back valid.
+        let strict_schema = json_schema::product_schema_with(&SchemaOptions { strict: true });
+        let strict_validator = jsonschema::validator_for(&strict_schema).unwrap();
+        let both = [
+            json!({ "Name": "Yo-yo", "Dollars": 6.0, "Sale": 25.5 }),
+            json!({ "Name": "Yo-yo", "USD": "6.50" }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": "" }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": null }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": {} }),
+            json!({ "Name": "Yo-yo", "Sale": { "SalePrice": { "Euros": "2" } }, "EUR": 3 }),
+            json!({ "Name": "Yo-yo", "Price": { "@currency": "USD", "$text": "6.50" } }),
+            json!({ "Name": "Yo-yo", "Price": { "@currency": "Dollars", "$text": 6 } }),
+            json!({ "Name": "Yo-yo", "Price": { "JPY": 1200 } }),
+        ];
+        let lenient_only = [
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Color": "red" }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Euros": 5 }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Price": { "@currency": "EUR", "$text": 3 } }),
+            json!({ "Name": "Yo-yo", "Price": { "@currency": "USD", "$text": 6, "Note": 1 } }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": { "PercentOff": 10, "Color": 1 } }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": { "AmountOff": { "Dollars": 1, "Note": 1 } } }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": { "PercentOff": 10, "SalePrice": { "USD": 5 } } }),
+        ];
+        let neither = [
+            json!({ "Name": "Yo-yo", "Sale": null }),
+            json!({ "Name": "Yo-yo", "Doubloons": 3 }),
+            json!({ "Name": "Yo-yo", "Dollars": "6.001" }),
+            json!({ "Name": "Yo-yo", "JPY": "6.5" }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": { "BOGO": 1 } }),
+            json!({ "Name": "Yo-yo", "Dollars": 6, "Sale": true }),
+            json!({ "Name": "Yo-yo", "Price": { "@currency": "USD" } }),
+            json!({ "Name": "Yo-yo", "Price": { "@currency": "JPY", "$text": "6.5" } }),
+            json!({ "Dollars": 6 }),
+        ];
+        let cases = both
+            .iter()
+            .map(|document| (document, true, true))
+            .chain(lenient_only.iter().map(|document| (document, true, false)))
+            .chain(neither.iter().map(|document| (document, false, false)));
+        for (document, lenient, strict) in cases {
+            let input = document.to_string();
+            assert_eq!(validator.is_valid(document), lenient, "{document}");
+            let res = json::from_json_str::<Product>(&input);
+            assert_eq!(res.is_ok(), lenient, "{document}: {res:?}");
+            assert_eq!(strict_validator.is_valid(document), strict, "{document}");
+            let strict_res = flatten::with_strict(true, || json::from_json_str::<Product>(&input));
+            assert_eq!(strict_res.is_ok(), strict, "{document}: {strict_res:?}");
+            if let Ok(product) = res {
+                let out: Value =
+                    serde_json::from_str(&json::to_json_string(&product).unwrap()).unwrap();
+                assert!(validator.is_valid(&out), "{out}");
+                assert_eq!(
+                    strict_validator.is_valid(&out),
+                    product.extra.is_empty(),
+                    "{out}"
+                );
+            }
+        }
+
+        let currency = jsonschema::validator_for(&json_schema::currency_schema()).unwrap();
+        assert!(currency.is_valid(&json!({ "Euros": 2.5 })));
+        assert!(currency.is_valid(&json!({ "Euros": 2.5, "Name": "Top" })));
+        let strict_currency = json_schema::currency_schema_with(&SchemaOptions { strict: true });
+        let strict_currency = jsonschema::validator_for(&strict_currency).unwrap();
+        assert!(!strict_currency.is_valid(&json!({ "Euros": 2.5, "Name": "Top" })));
+        let sale = jsonschema::validator_for(&json_schema::sale_schema()).unwrap();
+        assert!(sale.is_valid(&json!({ "AmountOff": { "Dollars": 1 } })));
+        assert!(!sale.is_valid(&json!({ "AmountOff": 1 })));
+    }
+
+    #[test]
+    fn strict_read_rejects_what_lenient_read_ignores() {
+        let strict = ReadOptions {
+            strict: true,
+            ..ReadOptions::default()
+        };
+        let lenient = ReadOptions::default();
+
+        let input = "<Product><Name>Yo-yo</Name><Dollars>6</Dollars><Euros>5</Euros></Product>";
+        let res: Product = xml::from_str_with(input, &lenient).expect("should have deserialized");
+        assert_eq!(res.price, Currency::Dollars(Amount::from_major(6)));
+        let err = xml::from_str_with::<Product>(input, &strict).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                XmlError::DuplicateElement { ref first, ref second }
+                    if first == "Dollars" && second == "Euros"
+            ),
+            "{err:?}"
+        );
+
+        let input = "<Product><Name>Yo-yo</Name><Dollars>6</Dollars><Color>red</Color></Product>";
+        xml::from_str_with::<Product>(input, &lenient).expect("should have deserialized");
+        let err = xml::from_str_with::<Product>(input, &strict).unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnexpectedElement(ref element) if element == "Color"),
+            "{err:?}"
+        );
+
+        let input = "<Product><Name>Yo-yo</Name><Dollars>6</Dollars>\
+                     <Sale><PercentOff>10</PercentOff><Color>red</Color></Sale></Product>";
+        xml::from_str_with::<Product>(input, &lenient).expect("should have deserialized");
+        let err = xml::from_str_with::<Product>(input, &strict).unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnexpectedElement(ref element) if element == "Color"),
+            "{err:?}"
+        );
+
+        let input = "<Product><Name>Yo-yo</Name><Dollars>6</Dollars>\
+                     <Sale><PercentOff>10</PercentOff></Sale><Sale/></Product>";
+        for options in [&lenient, &strict] {
+            let err = xml::from_str_with::<Product>(input, options).unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    XmlError::DuplicateElement { ref first, ref second }
+                        if first == "Sale" && second == "Sale"
+                ),
+                "{err:?}"
+            );
+            assert_eq!(err.to_string(), "duplicate element <Sale>");
+        }
+    }
+
+    #[test]
+    fn unknown_elements_round_trip_in_order() {
+        let input = "<Product><Sku id=\"42\">YY-1</Sku><Name>Yo-yo</Name><Dollars>6</Dollars>\
+                     <Color>red</Color><Dims><W unit=\"cm\">3</W><H>4</H></Dims>\
+                     <Color>blue</Color><Sale/></Product>";
+
+        let res: Product = xml::from_str(input).expect("should have deserialized");
+        assert_eq!(res.price, Currency::Dollars(Amount::from_major(6)));
+        let elements: Vec<&str> = res.extra.0.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(elements, ["Sku", "Color", "Dims", "Color"]);
+        assert_eq!(
+            res.extra.get("Color").and_then(|value| value.text()),
+            Some("red")
+        );
+
+        let out = xml::to_string(&res).expect("should have serialized");
+        assert_eq!(
+            out,
+            "<Product><Name>Yo-yo</Name><Dollars>6.00</Dollars><Sale/><Sku id=\"42\">YY-1</Sku>\
+             <Color>red</Color><Dims><W unit=\"cm\">3</W><H>4</H></Dims><Color>blue</Color>\
+             </Product>"
+        );
+        let again: Product = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(again, res);
+
+        let json = super::json::to_json_string(&res).expect("should have serialized");
+        let from_json: Product =
+            super::json::from_json_str(&json).expect("should have deserialized");
+        assert_eq!(from_json.extra.0.len(), 4, "{json}");
+    }
+
+    #[test]
+    fn price_reads_and_writes_either_currency_encoding() {
+        let input =
+            "<Product><Name>Kendama</Name><Price currency=\"JPY\">1200</Price><Sale/></Product>";
+        let res: Product = xml::from_str(input).expect("should have deserialized");
+        let jpy = CurrencyCode::from_alpha("JPY").unwrap();
+        assert_eq!(res.price, Currency::from_minor(jpy, 1200));
+        assert!(res.extra.is_empty(), "{:?}", res.extra);
+
+        let attribute = WriteOptions {
+            currency_encoding: CurrencyEncoding::Attribute,
+            ..WriteOptions::default()
+        };
+        assert_eq!(xml::to_string_with(&res, &attribute).unwrap(), input);
+        assert_eq!(
+            xml::to_string(&res).unwrap(),
+            "<Product><Name>Kendama</Name><JPY>1200</JPY><Sale/></Product>"
+        );
+
+        let input = "<Product><Name>Yo-yo</Name><Price currency=\"Dollars\">6.5</Price></Product>";
+        let res: Product = xml::from_str(input).expect("should have deserialized");
+        assert_eq!(res.price, Currency::Dollars("6.50".parse().unwrap()));
+        assert_eq!(
+            xml::to_string_with(&res, &attribute).unwrap(),
+            "<Product><Name>Yo-yo</Name><Price currency=\"USD\">6.50</Price><Sale/></Product>"
+        );
+
+        let err = xml::from_str::<Product>(
+            "<Product><Name>Yo-yo</Name><Price currency=\"XYZ\">6</Price></Product>",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnknownCurrency(ref code) if code == "XYZ"),
+            "{err:?}"
+        );
+        let err = xml::from_str::<Product>(
+            "<Product><Name>Yo-yo</Name><Price currency=\"USD\">6.001</Price></Product>",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, XmlError::InvalidNumber { ref element, .. } if element == "Price"),
+            "{err:?}"
+        );
+
+        let strict = ReadOptions {
+            strict: true,
+            ..ReadOptions::default()
+        };
+        let input = "<Product><Name>Yo-yo</Name><Price currency=\"USD\">6</Price>\
+                     <Dollars>6</Dollars></Product>";
+        let err = xml::from_str_with::<Product>(input, &strict).unwrap_err();
+        assert!(
+            matches!(err, XmlError::DuplicateElement { ref second, .. } if second == "Dollars"),
+            "{err:?}"
+        );
+
+        // Whichever currency comes first wins, whatever its encoding.
+        let input = "<Product><Name>Yo-yo</Name><Dollars>6</Dollars>\
+                     <Price currency=\"EUR\">3</Price></Product>";
+        let res: Product = xml::from_str(input).expect("should have deserialized");
+        assert_eq!(res.price, Currency::Dollars(Amount::from_major(6)));
+        assert!(res.extra.is_empty(), "{:?}", res.extra);
+        let err = xml::from_str_with::<Product>(input, &strict).unwrap_err();
+        assert!(
+            matches!(err, XmlError::DuplicateElement { ref first, ref second }
+                if first == "Dollars" && second == "Price"),
+            "{err:?}"
+        );
+        let input = "<Product><Name>Yo-yo</Name><Price currency=\"EUR\">3</Price>\
+                     <Dollars>6</Dollars></Product>";
+        let res: Product = xml::from_str(input).expect("should have deserialized");
+        assert_eq!(res.price, Currency::Euros(Amount::from_major(3)));
+    }
+
+    #[test]
+    fn currency_attribute_is_selectable_per_field() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Invoice {
+            #[serde(rename = "Total", with = "super::types::currency_attribute")]
+            total: Currency,
+            #[serde(rename = "Shipping")]
+            shipping: Currency,
+        }
+
+        let obj = Invoice {
+            total: Currency::Euros("12.50".parse().unwrap()),
+            shipping: Currency::Euros("2.50".parse().unwrap()),
+        };
+        let out = quick_xml::se::to_string_with_root("Invoice", &obj).unwrap();
+        assert_eq!(
+            out,
+            "<Invoice><Total currency=\"EUR\">12.50</Total><Shipping><Euros>2.50</Euros></Shipping></Invoice>"
+        );
+        let res: Invoice = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj);
+
+        // The attribute field also takes a variant element, for feeds in between.
+        let res: Invoice = xml::from_str(
+            "<Invoice><Total><EUR>12.5</EUR></Total><Shipping><Euros>2.5</Euros></Shipping></Invoice>",
+        )
+        .expect("should have deserialized");
+        assert_eq!(res, obj);
+    }
+
+    #[test]
+    fn tagged_newtype_enums_round_trip() {
+        use super::tagged::{self, KindAmount, TypeValue};
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Offer {
+            #[serde(
+                rename = "Price",
+                serialize_with = "tagged::serialize_adjacent::<KindAmount, _, _>",
+                deserialize_with = "tagged::deserialize_adjacent::<KindAmount, _, _>"
+            )]
+            price: Currency,
+            #[serde(
+                rename = "Sale",
+                serialize_with = "tagged::serialize_internal::<KindAmount, _, _>",
+                deserialize_with = "tagged::deserialize_internal::<KindAmount, _, _>"
+            )]
+            sale: Sale,
+        }
+
+        let obj = Offer {
+            price: Currency::Dollars(Amount::from_major(6)),
+            sale: Sale::AmountOff(Currency::Dollars(Amount::from_major(1))),
+        };
+        let out = quick_xml::se::to_string_with_root("Offer", &obj).unwrap();
+        assert_eq!(
+            out,
+            "<Offer><Price><Kind>Dollars</Kind><Amount>6.00</Amount></Price>\
+             <Sale><Kind>AmountOff</Kind><Dollars>1.00</Dollars></Sale></Offer>"
+        );
+        let res: Offer = xml::from_str(&out).expect("should have deserialized");
+        assert_eq!(res, obj);
+
+        let input = "<Offer><Price><Amount>1200</Amount><Kind>JPY</Kind></Price>\
+                     <Sale><Kind>PercentOff</Kind>10</Sale></Offer>";
+        let res: Offer = xml::from_str(input).expect("should have deserialized");
+        let jpy = CurrencyCode::from_alpha("JPY").unwrap();
+        assert_eq!(res.price, Currency::from_minor(jpy, 1200));
+        assert_eq!(res.sale, Sale::PercentOff(Amount::from_major(10)));
+        assert_eq!(
+            quick_xml::se::to_string_with_root("Offer", &res).unwrap(),
+            "<Offer><Price><Kind>JPY</Kind><Amount>1200</Amount></Price>\
+             <Sale><Kind>PercentOff</Kind>10.00</Sale></Offer>"
+        );
+
+        let err = xml::from_str::<Offer>(
+            "<Offer><Price><Kind>Pesos</Kind><Amount>6</Amount></Price>\
+             <Sale><Kind>PercentOff</Kind>10</Sale></Offer>",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, XmlError::UnknownCurrency(ref kind) if kind == "Pesos"),
+            "{err:?}"
+        );
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Quote {
+            #[serde(
+                serialize_with = "tagged::serialize_adjacent::<TypeValue, _, _>",
+                deserialize_with = "tagged::deserialize_adjacent::<TypeValue, _, _>"
+            )]
+            price: Currency,
+        }
+
+        let res: Quote = super::json::from_json_str(r#"{"price":{"type":"Dollars","value":6}}"#)
+            .expect("should have deserialized");
+        assert_eq!(res.price, Currency::Dollars(Amount::from_major(6)));
+        assert_eq!(
+            super::json::to_json_string(&res).unwrap(),
+            r#"{"price":{"type":"Dollars","value":6.0}}"#
+        );
+    }
+
+    #[test]
+    fn namespaced_documents_read_and_write() {
+        const PARTNER: &str = "urn:partner:catalog:v2";
+        let options = ReadOptions {
+            root: Some("Product".to_string()),
+            namespace: Some(PARTNER.to_string()),
+            ..ReadOptions::default()
+        };
+        let obj = Product {
+            name: "Yo-yo".to_string(),
+            price: Currency::Dollars(Amount::from_major(6)),
+            sale: Some(Sale::PercentOff(Amount::from_major(10))),
+            extra: Extra::default(),
+        };
+
+        let input = "<Product xmlns=\"urn:partner:catalog:v2\"><Name>Yo-yo</Name>\
+                     <Dollars>6</Dollars><Sale><PercentOff>10</PercentOff></Sale></Product>";
+        let res: Product = xml::from_str_with(input, &options).expect("should have deserialized");
+        assert_eq!(res, obj);
+
+        let input = "<p:Product xmlns:p=\"urn:partner:catalog:v2\" xmlns:x=\"urn:other\">\
+                     <p:Name>Yo-yo</p:Name><x:Dollars>1</x:Dollars><p:Dollars>6</p:Dollars>\
+                     <x:Color x:shade=\"dark\">red</x:Color><p:Sale><p:PercentOff>10</p:PercentOff></p:Sale></p:Product>";
+        let res: Product = xml::from_str_with(input, &options).expect("should have deserialized");
+        assert_eq!(
+            (&res.name, &res.price, &res.sale),
+            (&obj.name, &obj.price, &obj.sale)
+        );
+        let elements: Vec<&str> = res.extra.0.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(elements, ["@xmlns:x", "x:Dollars", "x:Color"]);
+
+        let prefixed = WriteOptions {
+            namespace: Some(xml::Namespace {
+                uri: PARTNER.to_string(),
+                prefix: Some("p".to_string()),
+            }),
+            ..WriteOptions::default()
+        };
+        let out = xml::to_string_with(&res, &prefixed).expect("should have serialized");
+        assert_eq!(
+            out,
+            "<p:Product xmlns:p=\"urn:partner:catalog:v2\" xmlns:x=\"urn:other\">\
+             <p:Name>Yo-yo</p:Name><p:Dollars>6.00</p:Dollars>\
+             <p:Sale><p:PercentOff>10.00</p:PercentOff></p:Sale><x:Dollars>1</x:Dollars>\
+             <x:Color x:shade=\"dark\">red</x:Color></p:Product>"
+        );
+        let again: Product = xml::from_str_with(&out, &options).expect("should have deserialized");
+        assert_eq!(again, res);
+
+        let default = WriteOptions {
+            namespace: Some(xml::Namespace {
+                uri: PARTNER.to_string(),
+                prefix: None,
+            }),
+            ..WriteOptions::default()
+        };
+        let out = xml::to_string_with(&obj, &default).expect("should have serialized");
+        assert!(
+            out.starts_with("<Product xmlns=\"urn:partner:catalog:v2\"><Name>"),
+            "{out}"
+        );
+        let again: Product = xml::from_str_with(&out, &options).expect("should have deserialized");
+        assert_eq!(again, obj);
+
+        let err = xml::from_str_with::<Product>(
+            "<Product><q:Name>Yo-yo</q:Name><Dollars>6</Dollars></Product>",
+            &options,
+        )
+        .unwrap_err();
+        assert!(matches!(err, XmlError::Syntax { line: 1, .. }), "{err:?}");
+
+        // A foreign default namespace is foreign too, not the absence of a prefix.
+        let input = "<Product xmlns=\"urn:partner:catalog:v2\"><Name>Yo-yo</Name>\
+                     <Dollars xmlns=\"urn:other\">1</Dollars><Dollars>6</Dollars>\
+                     <Label xmlns=\"urn:other\"><Text>Hi</Text></Label>\
+                     <Sale><PercentOff>10</PercentOff></Sale></Product>";
+        let res: Product = xml::from_str_with(input, &options).expect("should have deserialized");
+        assert_eq!(
+            (&res.name, &res.price, &res.sale),
+            (&obj.name, &obj.price, &obj.sale)
+        );
+        let elements: Vec<&str> = res.extra.0.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(elements, ["ns1:Dollars", "ns1:Label"]);
+        let out = xml::to_string_with(&res, &default).expect("should have serialized");
+        assert!(
+            out.ends_with(
+                "<ns1:Dollars xmlns:ns1=\"urn:other\">1</ns1:Dollars>\
+                 <ns1:Label xmlns:ns1=\"urn:other\"><ns1:Text>Hi</ns1:Text></ns1:Label></Product>"
+            ),
+            "{out}"
+        );
+        let again: Product = xml::from_str_with(&out, &options).expect("should have deserialized");
+        assert_eq!(again, res);
+    }
+
+    #[test]
+    fn pretty_output_reads_back_identically() {
+        use super::xml::LineEnding;
+
+        let jpy = CurrencyCode::from_alpha("JPY").unwrap();
+        let product = |price, sale| Product {
+            name: "Yo-yo\nDeluxe".to_string(),
+            price,
+            sale,
+            extra: Extra::default(),
+        };
+        let products = [
+            product(Currency::Dollars(Amount::from_major(6)), None),
+            product(
+                Currency::Euros(Amount::from_major(6)),
+                Some(Sale::PercentOff(Amount::from_major(10))),
+            ),
+            product(
+                Currency::from_minor(jpy, 1200),
+                Some(Sale::AmountOff(Currency::from_minor(jpy, 200))),
+            ),
+            product(
+                Currency::Dollars(Amount::from_major(6)),
+                Some(Sale::SalePrice(Currency::Dollars(Amount::from_major(5)))),
+            ),
+        ];
+        let file_path = temp_path("pretty.xml");
+
+        for indent in [
+            None,
+            Some(Indent { char: ' ', size: 4 }),
+            Some(Indent {
+                char: '\t',
+                size: 1,
+            }),
+        ] {
+            for line_ending in [LineEnding::Lf, LineEnding::CrLf] {
+                for expand_empty_elements in [false, true] {
+                    let options = WriteOptions {
+                        declaration: true,
+                        indent,
+                        line_ending,
+                        expand_empty_elements,
+                        ..WriteOptions::default()
+                    };
+                    for obj in &products {
+                        xml::to_xml_file_with(&file_path, obj, &options)
+                            .expect("should have serialized");
+                        let res = from_xml_file(&file_path).expect("should have deserialized");
+                        assert_eq!(&res, obj, "{options:?}");
+                    }
+                }
+            }
+        }
+
+        let options = WriteOptions {
+            indent: Some(Indent { char: ' ', size: 2 }),
+            line_ending: LineEnding::CrLf,
+            expand_empty_elements: true,
+            ..WriteOptions::default()
+        };
+        let out = xml::to_string_with(&products[0], &options).expect("should have serialized");
+        assert_eq!(
+            out,
+            "<Product>\r\n  <Name>Yo-yo\nDeluxe</Name>\r\n  <Dollars>6.00</Dollars>\r\n  \
+             <Sale></Sale>\r\n</Product>"
+        );
+        fs::remove_file(&file_path).expect("should remove the fixture");
+    }
+
+    #[test]
+    fn no_sale_policies_round_trip() {
+        use super::catalog::Catalog;
+
+        let yoyo = |sale| Product {
+            name: "Yo-yo".to_string(),
+            price: Currency::Dollars(Amount::from_major(6)),
+            sale,
+            extra: Extra::default(),
+        };
+        let product = yoyo(None);
+        for (no_sale, expected) in [
+            (
+                NoSale::Omit,
+                "<Product><Name>Yo-yo</Name><Dollars>6.00</Dollars></Product>",
+            ),
+            (
+                NoSale::Empty,
+                "<Product><Name>Yo-yo</Name><Dollars>6.00</Dollars><Sale/></Product>",
+            ),
+            (
+                NoSale::Nil,
+                "<Product><Name>Yo-yo</Name><Dollars>6.00</Dollars>\
+                 <Sale xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:nil=\"true\"/>\
+                 </Product>",
+            ),
+        ] {
+            let options = WriteOptions {
+                no_sale,
+                ..WriteOptions::default()
+            };
+            let out = xml::to_string_with(&product, &options).expect("should have serialized");
+            assert_eq!(out, expected);
+            let res: Product = xml::from_str(&out).expect("should have deserialized");
+            assert_eq!(res, product, "{no_sale:?}");
+            let res: Product = xml::from_str_with(
+                &out,
+                &ReadOptions {
+                    strict: true,
+                    ..ReadOptions::default()
+                },
+            )
+            .expect("should have deserialized strictly");
+            assert_eq!(res, product, "{no_sale:?}");
+
+            let catalog = Catalog {
+                products: vec![yoyo(None), yoyo(None)],
+            };
+            let options = WriteOptions {
+                root: "Catalog".to_string(),
+                ..options
+            };
+            let out = xml::to_string_with(&catalog, &options).expect("should have serialized");
+            let res: Catalog = xml::from_str(&out).expect("should have deserialized");
+            assert_eq!(res, catalog, "{no_sale:?}");
+        }
+
+        // The policy only applies to missing sales.
+        let on_sale = yoyo(Some(Sale::PercentOff(Amount::from_major(10))));
+        let options = WriteOptions {
+            no_sale: NoSale::Omit,
+            ..WriteOptions::default()
+        };
+        let out = xml::to_string_with(&on_sale, &options).expect("should have serialized");
+        assert!(
+            out.contains("<Sale><PercentOff>10.00</PercentOff></Sale>"),
+            "{out}"
+        );
+
+        // Other spellings of nil, also read namespace-aware.
+        for sale in [
+            "<Sale xsi:nil=\"1\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"></Sale>",
+            "<Sale xmlns:i=\"http://www.w3.org/2001/XMLSchema-instance\" i:nil=\"true\"/>",
+            "<Sale xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"/>",
+        ] {
+            let input =
+                format!("<Product><Name>Yo-yo</Name><Dollars>6.00</Dollars>{sale}</Product>");
+            let res: Product = xml::from_str(&input).expect("should have deserialized");
+            assert_eq!(res, product, "{sale}");
+            let res: Product = xml::from_str_with(
+                &input,
+                &ReadOptions {
+                    namespace: Some("urn:example:products".to_string()),
+                    ..ReadOptions::default()
+                },
+            )
+            .expect("should have deserialized namespace-aware");
+            assert_eq!(res, product, "{sale}");
+        }
     }
 }
@@ -0,0 +1,397 @@
+//! Documents holding many products: `<Catalog><Product>...</Product>...</Catalog>`.
+//!
+//! Small catalogs can be read whole into a [`Catalog`] with the functions in
+//! [`xml`](super::xml). Supplier feeds can be too large for that, so
+//! [`CatalogReader`] walks the document and deserializes one `<Product>` at a time,
+//! holding no more than a single product's markup in memory. [`CatalogWriter`]
+//! does the same in the other direction.
+
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use quick_xml::{Reader, Writer, events::Event};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Product,
+    types::{CurrencyEncoding, CurrencyNaming, NoSale},
+    xml::{self, WriteOptions, XmlError},
+};
+
+/// Name of a catalog document's root element.
+pub const ROOT: &str = "Catalog";
+
+/// Name of the elements a catalog holds its products in.
+pub const PRODUCT: &str = "Product";
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Catalog {
+    #[serde(rename = "Product", default)]
+    pub products: Vec<Product>,
+}
+
+/// A product in a catalog that could not be read.
+#[derive(Debug)]
+pub struct CatalogError {
+    /// 0-based index of the product among the catalog's `<Product>` elements.
+    pub index: usize,
+    /// Byte offset of the product's start tag, or of the syntax error.
+    pub position: u64,
+    /// 1-based line of `position`.
+    pub line: usize,
+    /// What went wrong; positions inside are relative to the product's start tag.
+    pub source: XmlError,
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "product #{} at byte {} (line {}): {}",
+            self.index, self.position, self.line, self.source
+        )
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Iterates over the products of a `<Catalog>` document read from `R`.
+///
+/// A product that fails to deserialize yields an error and reading carries on with
+/// the next one. Malformed markup cannot be skipped reliably, so a syntax error is
+/// yielded once and ends the iteration.
+pub struct CatalogReader<R> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    product: Writer<Vec<u8>>,
+    index: usize,
+    line: usize,
+    started: bool,
+    done: bool,
+}
+
+impl CatalogReader<BufReader<File>> {
+    pub fn open(file_path: impl Into<PathBuf>) -> Result<Self, XmlError> {
+        Ok(Self::new(BufReader::new(File::open(file_path.into())?)))
+    }
+}
+
+impl<R: BufRead> CatalogReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: Reader::from_reader(reader),
+            buf: Vec::new(),
+            product: Writer::new(Vec::new()),
+            index: 0,
+            line: 1,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Reads the next event into `self.buf`, keeping track of the line it ends on.
+    ///
+    /// The buffer holds everything between the event's delimiters, which never
+    /// contain a line break, so counting the buffer's line breaks is exact.
+    fn read_event(&mut self) -> Result<Event<'static>, CatalogError> {
+        self.buf.clear();
+        match self.reader.read_event_into(&mut self.buf) {
+            Ok(event) => {
+                let event = event.into_owned();
+                self.line += self.buf.iter().filter(|&&b| b == b'\n').count();
+                Ok(event)
+            }
+            Err(source) => {
+                self.done = true;
+                let position = self.reader.error_position();
+                Err(CatalogError {
+                    index: self.index,
+                    position,
+                    line: self.line,
+                    source: XmlError::Syntax {
+                        position,
+                        line: self.line,
+                        source,
+                    },
+                })
+            }
+        }
+    }
+
+    /// An error that leaves the document unreadable past this point.
+    fn fail(&mut self, source: XmlError) -> CatalogError {
+        self.done = true;
+        CatalogError {
+            index: self.index,
+            position: self.reader.buffer_position(),
+            line: self.line,
+            source,
+        }
+    }
+
+    /// Copies the rest of the product whose start tag was just read into `self.product`.
+    fn read_product(&mut self) -> Result<(), CatalogError> {
+        let mut depth = 1;
+        while depth > 0 {
+            let event = self.read_event()?;
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                Event::Eof => return Err(self.fail(XmlError::MissingElement(PRODUCT.to_string()))),
+                _ => {}
+            }
+            self.product
+                .write_event(event)
+                .expect("writing to a Vec cannot fail");
+        }
+        Ok(())
+    }
+
+    /// Skips past the end of the element whose start tag was just read.
+    fn skip_element(&mut self) -> Result<(), CatalogError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.read_event()? {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                Event::Eof => return Ok(()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn next_product(&mut self) -> Option<Result<Product, CatalogError>> {
+        loop {
+            let position = self.reader.buffer_position();
+            let line = self.line;
+            let event = match self.read_event() {
+                Ok(event) => event,
+                Err(err) => return Some(Err(err)),
+            };
+            let name = match &event {
+                Event::Start(e) | Event::Empty(e) => e.name().as_ref().to_vec(),
+                Event::End(_) | Event::Eof if self.started => return None,
+                Event::Eof => return Some(Err(self.fail(XmlError::MissingElement(ROOT.into())))),
+                _ => continue,
+            };
+            let name = String::from_utf8_lossy(&name).into_owned();
+
+            if !self.started {
+                if name != ROOT {
+                    return Some(Err(self.fail(XmlError::UnexpectedRoot {
+                        expected: ROOT.to_string(),
+                        found: name,
+                    })));
+                }
+                self.started = true;
+                if matches!(event, Event::Empty(_)) {
+                    return None;
+                }
+                continue;
+            }
+
+            if name != PRODUCT {
+                if matches!(event, Event::Start(_))
+                    && let Err(err) = self.skip_element()
+                {
+                    return Some(Err(err));
+                }
+                continue;
+            }
+
+            let has_content = matches!(event, Event::Start(_));
+            self.product.get_mut().clear();
+            self.product
+                .write_event(event)
+                .expect("writing to a Vec cannot fail");
+            if has_content && let Err(err) = self.read_product() {
+                return Some(Err(err));
+            }
+
+            let index = self.index;
+            self.index += 1;
+            let markup = String::from_utf8_lossy(self.product.get_ref());
+            return Some(xml::from_str(&markup).map_err(|source| CatalogError {
+                index,
+                position,
+                line,
+                source,
+            }));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CatalogReader<R> {
+    type Item = Result<Product, CatalogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_product();
+        if next.is_none() {
+            self.done = true;
+        }
+        next
+    }
+}
+
+/// How [`CatalogWriter`] writes its document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogWriteOptions {
+    /// Whether to start the document with an `<?xml ...?>` declaration.
+    pub declaration: bool,
+    /// Whether USD and EUR are written as `<Dollars>`/`<Euros>` or by their ISO code.
+    pub currency_naming: CurrencyNaming,
+    /// Whether prices are written as `<Dollars>` or `<Price currency="USD">`.
+    pub currency_encoding: CurrencyEncoding,
+    /// Whether products without a sale have no `<Sale>`, `<Sale/>` or `<Sale xsi:nil="true"/>`.
+    pub no_sale: NoSale,
+    /// Flush to disk after this many products; `0` only flushes on `finish`.
+    pub flush_every: usize,
+}
+
+impl Default for CatalogWriteOptions {
+    fn default() -> Self {
+        Self {
+            declaration: true,
+            currency_naming: CurrencyNaming::Legacy,
+            currency_encoding: CurrencyEncoding::Element,
+            no_sale: NoSale::Empty,
+            flush_every: 100,
+        }
+    }
+}
+
+/// Writes a `<Catalog>` document one product at a time, one product per line.
+///
+/// Everything goes to a temporary file of its own next to the destination, which only
+/// replaces the destination on [`finish`](Self::finish). Dropping the writer
+/// without finishing removes the temporary file and leaves the destination as it was.
+pub struct CatalogWriter {
+    path: PathBuf,
+    temp_path: PathBuf,
+    out: Option<BufWriter<File>>,
+    product_options: WriteOptions,
+    flush_every: usize,
+    written: usize,
+    finished: bool,
+}
+
+impl CatalogWriter {
+    pub fn create(file_path: impl Into<PathBuf>) -> Result<Self, XmlError> {
+        Self::create_with(file_path, &CatalogWriteOptions::default())
+    }
+
+    pub fn create_with(
+        file_path: impl Into<PathBuf>,
+        options: &CatalogWriteOptions,
+    ) -> Result<Self, XmlError> {
+        let path = file_path.into();
+        let (temp_path, file) = create_temp(&path)?;
+
+        let mut writer = Self {
+            out: Some(BufWriter::new(file)),
+            path,
+            temp_path,
+            product_options: WriteOptions {
+                root: PRODUCT.to_string(),
+                currency_naming: options.currency_naming,
+                currency_encoding: options.currency_encoding,
+                no_sale: options.no_sale,
+                ..WriteOptions::default()
+            },
+            flush_every: options.flush_every,
+            written: 0,
+            finished: false,
+        };
+        let out = writer.out();
+        if options.declaration {
+            out.write_all(br#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            out.write_all(b"\n")?;
+        }
+        writeln!(out, "<{ROOT}>")?;
+        Ok(writer)
+    }
+
+    fn out(&mut self) -> &mut BufWriter<File> {
+        self.out.as_mut().expect("only `finish` takes the output")
+    }
+
+    /// Appends `product` to the catalog.
+    pub fn write(&mut self, product: &Product) -> Result<(), XmlError> {
+        let markup = xml::to_string_with(product, &self.product_options)?;
+        let out = self.out();
+        out.write_all(markup.as_bytes())?;
+        out.write_all(b"\n")?;
+
+        self.written += 1;
+        if self.flush_every > 0 && self.written.is_multiple_of(self.flush_every) {
+            self.out().flush()?;
+        }
+        Ok(())
+    }
+
+    /// Number of products written so far.
+    pub fn len(&self) -> usize {
+        self.written
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.written == 0
+    }
+
+    /// Closes the root element and moves the finished catalog into place.
+    pub fn finish(mut self) -> Result<(), XmlError> {
+        let mut out = self.out.take().expect("only `finish` takes the output");
+        writeln!(out, "</{ROOT}>")?;
+        let file = out.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        fs::rename(&self.temp_path, &self.path)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+/// Creates a temporary file next to `path` that no other writer uses, named
+/// `<name>.<pid>-<n>.tmp`, so writers racing for the same destination never share one.
+fn create_temp(path: &Path) -> io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    loop {
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        temp_name.push(format!(".{}-{n}.tmp", process::id()));
+        let temp_path = path.with_file_name(temp_name);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            // Left behind by an earlier process with the same pid.
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+impl Drop for CatalogWriter {
+    fn drop(&mut self) {
+        if !self.finished {
+            drop(self.out.take());
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
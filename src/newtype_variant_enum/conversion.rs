@@ -0,0 +1,404 @@
+//! Converting a [`Currency`] amount into another currency.
+//!
+//! Rates come from a [`RateProvider`]; [`StaticRates`] holds them in memory and
+//! [`FileRates`] loads them from a CSV or XML file. All arithmetic is exact: the
+//! result is only rounded once, to the target currency's minor units, following
+//! the target's [`RoundingRule`].
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use derive_more::{Display, From};
+use serde::Deserialize;
+
+use super::{
+    types::{Currency, CurrencyCode, ParseAmountError, amount::parse_minor_units},
+    xml::{self, XmlError},
+};
+
+/// Exact exchange rate: `units / 10^scale` of the target per unit of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    units: i64,
+    scale: u8,
+}
+
+impl Rate {
+    pub const ONE: Self = Self { units: 1, scale: 0 };
+    /// Most fraction digits a rate may have, so that `10^scale` fits in a `u64`.
+    pub const MAX_SCALE: u8 = 18;
+
+    /// # Panics
+    ///
+    /// If `scale` is above [`Self::MAX_SCALE`].
+    pub const fn new(units: i64, scale: u8) -> Self {
+        assert!(scale <= Self::MAX_SCALE, "rate scale too large");
+        Self { units, scale }
+    }
+}
+
+impl FromStr for Rate {
+    type Err = ParseAmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fraction = s
+            .trim()
+            .split_once('.')
+            .map_or("", |(_, fraction)| fraction);
+        let scale = fraction.trim_end_matches('0').len();
+        let scale = u8::try_from(scale)
+            .ok()
+            .filter(|scale| *scale <= Self::MAX_SCALE)
+            .ok_or(ParseAmountError::Overflow)?;
+        parse_minor_units(s, scale).map(|units| Self::new(units, scale))
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        crate::newtype_variant_enum::types::amount::fmt_minor_units(f, self.units, self.scale)
+    }
+}
+
+/// Source of exchange rates.
+pub trait RateProvider {
+    /// Units of `to` per unit of `from`, or `None` if the rate is not known.
+    fn rate(&self, from: &CurrencyCode, to: &CurrencyCode) -> Option<Rate>;
+}
+
+/// Rates held in memory, keyed by ISO code pair.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StaticRates {
+    rates: HashMap<(&'static str, &'static str), Rate>,
+}
+
+impl StaticRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, from: &'static CurrencyCode, to: &'static CurrencyCode, rate: Rate) {
+        self.rates.insert((from.alpha, to.alpha), rate);
+    }
+
+    pub fn with(
+        mut self,
+        from: &'static CurrencyCode,
+        to: &'static CurrencyCode,
+        rate: Rate,
+    ) -> Self {
+        self.insert(from, to, rate);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.rates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+}
+
+impl RateProvider for StaticRates {
+    fn rate(&self, from: &CurrencyCode, to: &CurrencyCode) -> Option<Rate> {
+        self.rates.get(&(from.alpha, to.alpha)).copied()
+    }
+}
+
+/// Everything that can go wrong while loading a rates file.
+#[derive(Debug, Display, From)]
+pub enum RateFileError {
+    #[display("I/O error: {_0}")]
+    Io(std::io::Error),
+    #[display("{_0}")]
+    Xml(XmlError),
+    #[display("line {line}: {message}")]
+    #[from(skip)]
+    Invalid { line: usize, message: String },
+    #[display("unsupported rates file {_0:?}, expected .csv or .xml")]
+    #[from(skip)]
+    UnsupportedFormat(PathBuf),
+}
+
+impl std::error::Error for RateFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Xml(err) => Some(err),
+            Self::Invalid { .. } | Self::UnsupportedFormat(_) => None,
+        }
+    }
+}
+
+/// Rates loaded from a file on disk.
+///
+/// CSV files hold one `from,to,rate` row per line, optionally after a header row.
+/// Blank lines and lines starting with `#` are skipped, before the header too:
+///
+/// ```text
+/// # Rates as of 2026-10-18
+/// From,To,Rate
+/// USD,EUR,0.92
+/// ```
+///
+/// XML files hold `<Rate>` elements under any root element:
+///
+/// ```xml
+/// <Rates><Rate from="USD" to="EUR">0.92</Rate></Rates>
+/// ```
+///
+/// Either way, a rate of zero or below is rejected as [`RateFileError::Invalid`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRates {
+    path: PathBuf,
+    rates: StaticRates,
+}
+
+impl FileRates {
+    /// Loads `path`, picking the format from its `.csv` or `.xml` extension.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, RateFileError> {
+        let path = path.into();
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        let rates = match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("csv") => parse_csv(&fs::read_to_string(&path)?)?,
+            Some("xml") => parse_xml(&fs::read_to_string(&path)?)?,
+            _ => return Err(RateFileError::UnsupportedFormat(path)),
+        };
+        Ok(Self { path, rates })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn rates(&self) -> &StaticRates {
+        &self.rates
+    }
+}
+
+impl RateProvider for FileRates {
+    fn rate(&self, from: &CurrencyCode, to: &CurrencyCode) -> Option<Rate> {
+        self.rates.rate(from, to)
+    }
+}
+
+fn parse_entry(line: usize, from: &str, to: &str, rate: &str) -> Result<RateEntry, RateFileError> {
+    let invalid = |message: String| RateFileError::Invalid { line, message };
+    let code = |alpha: &str| {
+        CurrencyCode::from_alpha(alpha.trim())
+            .ok_or_else(|| invalid(format!("unknown currency code `{alpha}`")))
+    };
+    let (from, to) = (code(from)?, code(to)?);
+    let rate: Rate = rate
+        .trim()
+        .parse()
+        .map_err(|err| invalid(format!("invalid rate `{rate}`: {err}")))?;
+    if rate.units <= 0 {
+        return Err(invalid(format!("rate `{rate}` is not positive")));
+    }
+    Ok(RateEntry { from, to, rate })
+}
+
+struct RateEntry {
+    from: &'static CurrencyCode,
+    to: &'static CurrencyCode,
+    rate: Rate,
+}
+
+fn parse_csv(input: &str) -> Result<StaticRates, RateFileError> {
+    let mut rates = StaticRates::new();
+    let mut first_row = true;
+    for (index, row) in input.lines().enumerate() {
+        let line = index + 1;
+        let row = row.trim();
+        if row.is_empty() || row.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = row.split(',').collect();
+        let [from, to, rate] = fields[..] else {
+            return Err(RateFileError::Invalid {
+                line,
+                message: format!("expected 3 columns, found {}", fields.len()),
+            });
+        };
+        // A header may follow blank and comment lines, but no rates.
+        if std::mem::take(&mut first_row) && from.trim().eq_ignore_ascii_case("from") {
+            continue;
+        }
+        let entry = parse_entry(line, from, to, rate)?;
+        rates.insert(entry.from, entry.to, entry.rate);
+    }
+    Ok(rates)
+}
+
+#[derive(Deserialize)]
+struct XmlRates {
+    #[serde(rename = "Rate", default)]
+    rates: Vec<XmlRate>,
+}
+
+#[derive(Deserialize)]
+struct XmlRate {
+    #[serde(rename = "@from")]
+    from: String,
+    #[serde(rename = "@to")]
+    to: String,
+    #[serde(rename = "$text")]
+    rate: String,
+}
+
+fn parse_xml(input: &str) -> Result<StaticRates, RateFileError> {
+    let document: XmlRates = xml::from_str(input)?;
+    let mut rates = StaticRates::new();
+    for (index, rate) in document.rates.iter().enumerate() {
+        // Entries are numbered from 1 in document order, there is no line to report.
+        let entry = parse_entry(index + 1, &rate.from, &rate.to, &rate.rate)?;
+        rates.insert(entry.from, entry.to, entry.rate);
+    }
+    Ok(rates)
+}
+
+/// How a converted amount is brought back to the target's minor units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Round to nearest, ties to even (banker's rounding).
+    #[default]
+    HalfEven,
+    /// Round to nearest, ties away from zero.
+    HalfUp,
+    /// Toward zero.
+    Down,
+    /// Away from zero.
+    Up,
+}
+
+/// Rounding applied for one target currency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundingRule {
+    pub mode: RoundingMode,
+    /// Smallest step in minor units, e.g. 5 for Swiss cash amounts in 0.05 CHF.
+    pub increment: i64,
+}
+
+impl Default for RoundingRule {
+    fn default() -> Self {
+        Self {
+            mode: RoundingMode::HalfEven,
+            increment: 1,
+        }
+    }
+}
+
+/// Rounding rules by target currency, falling back to a default rule.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoundingRules {
+    pub default: RoundingRule,
+    rules: HashMap<&'static str, RoundingRule>,
+}
+
+impl RoundingRules {
+    pub fn with(mut self, currency: &'static CurrencyCode, rule: RoundingRule) -> Self {
+        self.rules.insert(currency.alpha, rule);
+        self
+    }
+
+    pub fn rule(&self, currency: &CurrencyCode) -> RoundingRule {
+        self.rules
+            .get(currency.alpha)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Everything that can go wrong while converting an amount.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum ConversionError {
+    #[display("no exchange rate from {from} to {to}")]
+    MissingRate { from: String, to: String },
+    #[display("converted amount does not fit")]
+    Overflow,
+    #[display("rounding increment must be positive, got {_0}")]
+    InvalidIncrement(i64),
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Converts `amount` into `target` with the default rounding rules.
+pub fn convert(
+    amount: &Currency,
+    target: &'static CurrencyCode,
+    provider: &dyn RateProvider,
+) -> Result<Currency, ConversionError> {
+    convert_with(amount, target, provider, &RoundingRules::default())
+}
+
+/// Converts `amount` into `target`, rounding by the target's rule in `rounding`.
+pub fn convert_with(
+    amount: &Currency,
+    target: &'static CurrencyCode,
+    provider: &dyn RateProvider,
+    rounding: &RoundingRules,
+) -> Result<Currency, ConversionError> {
+    let source = amount.code();
+    let rate = if source == target {
+        Rate::ONE
+    } else {
+        provider
+            .rate(source, target)
+            .ok_or_else(|| ConversionError::MissingRate {
+                from: source.alpha.to_string(),
+                to: target.alpha.to_string(),
+            })?
+    };
+
+    // target minor = source minor * rate * 10^target_scale / 10^(rate_scale + source_scale)
+    let numerator = i128::from(amount.minor_units())
+        .checked_mul(i128::from(rate.units))
+        .and_then(|n| n.checked_mul(pow10(target.minor_units)?))
+        .ok_or(ConversionError::Overflow)?;
+    let denominator = pow10(rate.scale)
+        .zip(pow10(source.minor_units))
+        .and_then(|(a, b)| a.checked_mul(b))
+        .ok_or(ConversionError::Overflow)?;
+
+    let rule = rounding.rule(target);
+    if rule.increment <= 0 {
+        return Err(ConversionError::InvalidIncrement(rule.increment));
+    }
+    let step = denominator
+        .checked_mul(i128::from(rule.increment))
+        .ok_or(ConversionError::Overflow)?;
+    let minor = round_div(numerator, step, rule.mode) * i128::from(rule.increment);
+    let minor = i64::try_from(minor).map_err(|_| ConversionError::Overflow)?;
+
+    Ok(Currency::from_minor(target, minor))
+}
+
+fn pow10(exponent: u8) -> Option<i128> {
+    10i128.checked_pow(u32::from(exponent))
+}
+
+/// `numerator / denominator` rounded by `mode`, for a positive `denominator`.
+pub(crate) fn round_div(numerator: i128, denominator: i128, mode: RoundingMode) -> i128 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    if remainder == 0 {
+        return quotient;
+    }
+    let away = quotient + numerator.signum();
+    let twice = 2 * remainder.abs();
+    let round_away = match mode {
+        RoundingMode::Down => false,
+        RoundingMode::Up => true,
+        RoundingMode::HalfUp => twice >= denominator,
+        RoundingMode::HalfEven => {
+            twice > denominator || (twice == denominator && quotient % 2 != 0)
+        }
+    };
+    if round_away { away } else { quotient }
+}
@@ -0,0 +1,263 @@
+//! Reading and writing products as CSV, one product per row:
+//!
+//! ```text
+//! Name,Currency,Amount,Sale
+//! Yo-yo,Dollars,6.00,25.50
+//! Kendama,JPY,1200,SalePrice 1000 JPY
+//! Top,Euros,2.50,
+//! ```
+//!
+//! The currency variant is split into a `Currency` column holding its element name
+//! and an `Amount` column holding its value. A `Sale` cell is empty for no sale, a
+//! bare number for a percentage off, as in legacy XML documents, optionally followed
+//! by `%`, or `AmountOff <amount> <code>` / `SalePrice <amount> <code>`, the parts
+//! separated by any whitespace. Columns are found by their header, so they may come
+//! in any order and `Sale` may be left out.
+//!
+//! Elements kept in a product's [`extra`](super::Product::extra) have no column.
+//! Writing a product that has some fails with [`CsvError::UnwrittenExtra`], unless
+//! [`WriteOptions::drop_extra`] says to leave them out.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::PathBuf,
+};
+
+use derive_more::{Display, From};
+
+use super::{
+    Product,
+    extra::Extra,
+    types::{
+        Currency, CurrencyNaming, FieldError, Money, ParseNumberError, Percent, Sale, iso4217,
+    },
+};
+
+/// Header of the columns written by [`to_csv_writer`].
+pub const HEADERS: [&str; 4] = ["Name", "Currency", "Amount", "Sale"];
+
+/// Everything that can go wrong while reading or writing products as CSV.
+#[derive(Debug, Display, From)]
+pub enum CsvError {
+    #[display("I/O error: {_0}")]
+    Io(std::io::Error),
+    #[display("malformed CSV: {_0}")]
+    #[from(skip)]
+    Malformed(::csv::Error),
+    #[display("missing column {_0:?}")]
+    #[from(skip)]
+    MissingColumn(String),
+    #[display("row {row}, column {column:?}: {source}")]
+    #[from(skip)]
+    InvalidCell {
+        /// 1-based line of the row, counting the header as line 1.
+        row: u64,
+        column: String,
+        source: FieldError,
+    },
+    #[display("row {row}: no column for elements {elements:?}")]
+    #[from(skip)]
+    UnwrittenExtra {
+        /// 1-based row of the product, counting the header as row 1.
+        row: u64,
+        elements: Vec<String>,
+    },
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Malformed(err) => Some(err),
+            Self::InvalidCell { source, .. } => Some(source),
+            Self::MissingColumn(_) | Self::UnwrittenExtra { .. } => None,
+        }
+    }
+}
+
+impl From<::csv::Error> for CsvError {
+    fn from(err: ::csv::Error) -> Self {
+        if err.is_io_error() {
+            Self::Io(err.into())
+        } else {
+            Self::Malformed(err)
+        }
+    }
+}
+
+/// How rows are written by [`to_csv_writer_with`] and [`to_csv_string_with`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteOptions {
+    /// Whether USD and EUR are written as `Dollars`/`Euros` or by their ISO code.
+    pub currency_naming: CurrencyNaming,
+    /// Leave out the elements kept in a product's `extra` instead of failing.
+    pub drop_extra: bool,
+}
+
+/// Reads every product from a CSV document with a header row.
+pub fn from_csv_reader<R: Read>(reader: R) -> Result<Vec<Product>, CsvError> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .trim(::csv::Trim::All)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let required = |name: &str| column(name).ok_or_else(|| CsvError::MissingColumn(name.into()));
+    let (name, currency, amount) = (
+        required("Name")?,
+        required("Currency")?,
+        required("Amount")?,
+    );
+    let sale = column("Sale");
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            let row = record.position().map_or(0, |position| position.line());
+            let cell = |index: usize| record.get(index).unwrap_or_default();
+            let invalid = |column: &str, source| CsvError::InvalidCell {
+                row,
+                column: column.to_string(),
+                source,
+            };
+
+            let price = match cell(currency) {
+                "" => Err(invalid("Currency", FieldError::Missing("Currency".into()))),
+                element => Currency::parse(element, cell(amount)).map_err(|err| match err {
+                    FieldError::InvalidNumber { .. } => invalid("Amount", err),
+                    _ => invalid("Currency", err),
+                }),
+            }?;
+            let sale = match sale {
+                Some(index) => parse_sale(cell(index)).map_err(|err| invalid("Sale", err))?,
+                None => None,
+            };
+            Ok(Product {
+                name: cell(name).to_string(),
+                price,
+                sale,
+                extra: Extra::default(),
+            })
+        })
+        .collect()
+}
+
+pub fn from_csv_str(input: &str) -> Result<Vec<Product>, CsvError> {
+    from_csv_reader(input.as_bytes())
+}
+
+pub fn from_csv_file(file_path: impl Into<PathBuf>) -> Result<Vec<Product>, CsvError> {
+    let source = File::open(file_path.into())?;
+    from_csv_reader(BufReader::new(source))
+}
+
+/// Reads a `Sale` cell, treating an empty one as no sale like
+/// [`parse_sale_or_empty_string`](super::types::parse_sale_or_empty_string).
+fn parse_sale(cell: &str) -> Result<Option<Sale>, FieldError> {
+    let cell = cell.trim();
+    if cell.is_empty() {
+        return Ok(None);
+    }
+    // Kinds are words, anything else is a percentage such as `25.50 %`.
+    let (kind, price) = match cell.split_once(char::is_whitespace) {
+        Some((kind, price)) if kind.starts_with(char::is_alphabetic) => (kind, price.trim()),
+        None if cell.starts_with(char::is_alphabetic) => (cell, ""),
+        _ => {
+            let percent = cell.strip_suffix('%').unwrap_or(cell).trim_end();
+            return percent
+                .parse::<Percent>()
+                .map(|percent| Some(Sale::PercentOff(percent)))
+                .map_err(|err| FieldError::InvalidNumber {
+                    element: "Sale".to_string(),
+                    value: cell.to_string(),
+                    ty: Percent::type_name(),
+                    source: ParseNumberError::Decimal(err),
+                });
+        }
+    };
+    let kind = match kind {
+        "AmountOff" => Sale::AmountOff,
+        "SalePrice" => Sale::SalePrice,
+        _ => {
+            return Err(FieldError::UnknownVariant {
+                enum_name: "Sale".to_string(),
+                element: kind.to_string(),
+            });
+        }
+    };
+    let (amount, code) = price
+        .rsplit_once(char::is_whitespace)
+        .ok_or_else(|| FieldError::Missing("Currency".to_string()))?;
+    Currency::parse(code, amount.trim()).map(|price| Some(kind(price)))
+}
+
+fn format_sale(sale: &Sale) -> String {
+    match sale {
+        Sale::PercentOff(percent) => percent.to_string(),
+        Sale::AmountOff(amount) => format!("AmountOff {amount}"),
+        Sale::SalePrice(price) => format!("SalePrice {price}"),
+    }
+}
+
+/// Writes `products` as a CSV document with a header row into `writer`.
+pub fn to_csv_writer<W: Write>(writer: W, products: &[Product]) -> Result<(), CsvError> {
+    to_csv_writer_with(writer, products, &WriteOptions::default())
+}
+
+pub fn to_csv_writer_with<W: Write>(
+    writer: W,
+    products: &[Product],
+    options: &WriteOptions,
+) -> Result<(), CsvError> {
+    let mut writer = ::csv::Writer::from_writer(writer);
+    writer.write_record(HEADERS)?;
+    iso4217::with_naming(options.currency_naming, || {
+        products.iter().zip(2..).try_for_each(|(product, row)| {
+            if !options.drop_extra && !product.extra.is_empty() {
+                return Err(CsvError::UnwrittenExtra {
+                    row,
+                    elements: product
+                        .extra
+                        .0
+                        .iter()
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                });
+            }
+            let price = &product.price;
+            let amount = Money::from_minor(price.code(), price.minor_units())
+                .amount()
+                .to_string();
+            writer.write_record([
+                product.name.as_str(),
+                price.element_name(),
+                &amount,
+                &product.sale.as_ref().map(format_sale).unwrap_or_default(),
+            ])?;
+            Ok(())
+        })
+    })?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn to_csv_string(products: &[Product]) -> Result<String, CsvError> {
+    to_csv_string_with(products, &WriteOptions::default())
+}
+
+pub fn to_csv_string_with(
+    products: &[Product],
+    options: &WriteOptions,
+) -> Result<String, CsvError> {
+    let mut out = Vec::new();
+    to_csv_writer_with(&mut out, products, options)?;
+    Ok(String::from_utf8(out).expect("products and headers are UTF-8"))
+}
+
+pub fn to_csv_file(file_path: impl Into<PathBuf>, products: &[Product]) -> Result<File, CsvError> {
+    let file = File::create(file_path.into())?;
+    to_csv_writer(&file, products)?;
+
+    Ok(file)
+}
@@ -0,0 +1,68 @@
+//! Child elements of a [`Product`](super::Product) that none of its fields read.
+//!
+//! Partners add elements such as `<Sku>` or `<Color>` to the products they send.
+//! Rather than dropping them, a product keeps them in an [`Extra`] bucket, in
+//! document order and with their attributes and nested content, and writes them
+//! back after its own elements:
+//!
+//! ```text
+//! <Product><Name>Yo-yo</Name><Dollars>6</Dollars><Sku id="42">YY-1</Sku></Product>
+//! ```
+//!
+//! Reading strictly (see [`flatten::with_strict`]) rejects such elements instead.
+
+use serde::{Deserializer, Serialize, Serializer, ser::SerializeMap};
+
+use super::flatten::{self, FlattenedEnum, Value};
+
+/// Unknown child elements, each with its content as read. Attributes show up as
+/// `@name` entries and text beside child elements as a `$text` entry, as quick-xml
+/// reads and writes them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Extra(pub Vec<(String, Value)>);
+
+impl Extra {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Content of the first element named `element`.
+    pub fn get(&self, element: &str) -> Option<&Value> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == element)
+            .map(|(_, value)| value)
+    }
+
+    /// Appends an element, written after those already present.
+    pub fn push(&mut self, element: impl Into<String>, value: Value) {
+        self.0.push((element.into(), value));
+    }
+}
+
+/// Written as map entries, so flattening an `Extra` yields one element per entry.
+impl Serialize for Extra {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (element, value) in &self.0 {
+            map.serialize_entry(element, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Reads the leftovers of a struct with a flattened `E` into an [`Extra`], leaving
+/// out the elements `E` reads. Use as `#[serde(flatten, deserialize_with = ...)]`.
+pub fn deserialize_besides<'de, D, E>(deserializer: D) -> Result<Extra, D::Error>
+where
+    D: Deserializer<'de>,
+    E: FlattenedEnum,
+{
+    let entries = flatten::entries(deserializer)?;
+    Ok(Extra(
+        entries
+            .into_iter()
+            .filter(|(element, _)| !E::is_variant(element))
+            .collect(),
+    ))
+}
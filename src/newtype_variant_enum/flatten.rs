@@ -0,0 +1,773 @@
+//! Runtime support for `#[derive(FlattenedNewtypeEnum)]`.
+//!
+//! When a field is `#[serde(flatten)]`, serde buffers the parent's leftover entries
+//! before handing them to the field's `Deserialize` impl. quick-xml produces those
+//! entries as strings (or as `{"$text": ...}` maps for elements), so the inner
+//! [`Amount`](super::types::Amount) of `<Dollars>6</Dollars>` can no longer be read
+//! with the inner type's own impl. [`Value`] buffers the entry again and its
+//! deserializer unwraps text and parses primitives out of it on demand, which makes
+//! any inner type work regardless of the data format.
+
+use std::{cell::Cell, fmt, str::FromStr};
+
+use derive_more::Display;
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{
+        self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+        SeqAccess, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+};
+
+use super::types::{FieldError, ParseNumberError, scoped};
+
+/// Key quick-xml uses for the text content of an element.
+pub const TEXT_KEY: &str = "$text";
+
+/// A self-describing buffered value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Text content, looking through quick-xml's `{"$text": ...}` element maps.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Self::Str(text) => Some(text),
+            Self::Map(entries) => match entries.as_slice() {
+                [] => Some(""),
+                [(key, Self::Str(text))] if key == TEXT_KEY => Some(text),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Deserializes `T` from this value, naming `element` in parse errors.
+    pub fn deserialize_into<T, E>(self, element: &str) -> Result<T, E>
+    where
+        T: DeserializeOwned,
+        E: de::Error,
+    {
+        T::deserialize(ValueDeserializer::new(self, element))
+            .map_err(|err| err.in_element(element).into_de())
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::I64(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::U64(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::F64(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::Str(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Str(String::from_utf8_lossy(v).into_owned()))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Unit)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Unit)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        // XML has no arrays: a repeated child element shows up as a repeated key,
+        // which is folded into a sequence at the key's first position.
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        let mut repeated: Vec<bool> = Vec::new();
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            match entries.iter().position(|(k, _)| *k == key) {
+                Some(i) if repeated[i] => match &mut entries[i].1 {
+                    Value::Seq(items) => items.push(value),
+                    _ => unreachable!("repeated entries hold a sequence"),
+                },
+                Some(i) => {
+                    let first = std::mem::replace(&mut entries[i].1, Value::Unit);
+                    entries[i].1 = Value::Seq(vec![first, value]);
+                    repeated[i] = true;
+                }
+                None => {
+                    entries.push((key, value));
+                    repeated.push(false);
+                }
+            }
+        }
+        Ok(Value::Map(entries))
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Text-only elements come back as plain strings rather than `$text` maps.
+        if let Some(text) = self.text() {
+            return serializer.serialize_str(text);
+        }
+        match self {
+            Self::Unit => serializer.serialize_unit(),
+            Self::Bool(v) => serializer.serialize_bool(*v),
+            Self::I64(v) => serializer.serialize_i64(*v),
+            Self::U64(v) => serializer.serialize_u64(*v),
+            Self::F64(v) => serializer.serialize_f64(*v),
+            Self::Seq(items) => serializer.collect_seq(items),
+            Self::Map(entries) => {
+                serializer.collect_map(entries.iter().map(|(key, value)| (key, value)))
+            }
+            Self::Str(_) => unreachable!("strings are handled as text"),
+        }
+    }
+}
+
+/// Error raised while deserializing from a [`Value`], keeping the [`FieldError`]
+/// behind it, if any, for [`into_de`](Self::into_de) to raise again.
+#[derive(Debug, Display)]
+#[display("{message}")]
+pub struct ValueError {
+    message: String,
+    field: Option<FieldError>,
+}
+
+impl std::error::Error for ValueError {}
+
+impl de::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        let message = msg.to_string();
+        let field = FieldError::take_raised(&message);
+        Self { message, field }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        FieldError::Missing(field.to_string()).into()
+    }
+
+    fn duplicate_field(field: &'static str) -> Self {
+        FieldError::Duplicate {
+            first: field.to_string(),
+            second: field.to_string(),
+        }
+        .into()
+    }
+}
+
+impl From<FieldError> for ValueError {
+    fn from(field: FieldError) -> Self {
+        Self {
+            message: field.to_string(),
+            field: Some(field),
+        }
+    }
+}
+
+impl ValueError {
+    /// The same failure as an error of the enclosing deserializer.
+    pub fn into_de<E: de::Error>(self) -> E {
+        match self.field {
+            Some(field) => field.raise(),
+            None => E::custom(self.message),
+        }
+    }
+
+    /// Names `element` in a number error raised by a type that could not know it,
+    /// such as [`Amount`](super::types::Amount).
+    fn in_element(self, element: &str) -> Self {
+        match self.field {
+            Some(FieldError::InvalidNumber {
+                element: unnamed,
+                value,
+                ty,
+                source,
+            }) if unnamed.is_empty() => FieldError::InvalidNumber {
+                element: element.to_string(),
+                value,
+                ty,
+                source,
+            }
+            .into(),
+            field => Self { field, ..self },
+        }
+    }
+}
+
+/// Lenient deserializer over a [`Value`]: primitives are parsed from text when the
+/// format only gave us text, and text-only elements can stand in for scalars.
+pub struct ValueDeserializer {
+    value: Value,
+    element: String,
+}
+
+impl ValueDeserializer {
+    pub fn new(value: Value, element: &str) -> Self {
+        Self {
+            value,
+            element: element.to_string(),
+        }
+    }
+
+    fn parse<T>(&self, text: &str, ty: &str) -> Result<T, ValueError>
+    where
+        T: FromStr,
+        T::Err: Into<ParseNumberError>,
+    {
+        text.trim().parse::<T>().map_err(|err| {
+            FieldError::InvalidNumber {
+                element: self.element.clone(),
+                value: text.to_string(),
+                ty: ty.to_string(),
+                source: err.into(),
+            }
+            .into()
+        })
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $ty:ident, $visit:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+                match self.value.text() {
+                    Some(text) => visitor.$visit(self.parse::<$ty>(text, stringify!($ty))?),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        if let Some(text) = self.value.text() {
+            return visitor.visit_string(text.to_string());
+        }
+        match self.value {
+            Value::Unit => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::I64(v) => visitor.visit_i64(v),
+            Value::U64(v) => visitor.visit_u64(v),
+            Value::F64(v) => visitor.visit_f64(v),
+            Value::Seq(items) => visitor.visit_seq(ValueSeqAccess::new(items, self.element)),
+            Value::Map(entries) => visitor.visit_map(ValueMapAccess::new(entries)),
+            Value::Str(_) => unreachable!("strings are handled as text"),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => i8, visit_i8;
+        deserialize_i16 => i16, visit_i16;
+        deserialize_i32 => i32, visit_i32;
+        deserialize_i64 => i64, visit_i64;
+        deserialize_i128 => i128, visit_i128;
+        deserialize_u8 => u8, visit_u8;
+        deserialize_u16 => u16, visit_u16;
+        deserialize_u32 => u32, visit_u32;
+        deserialize_u64 => u64, visit_u64;
+        deserialize_u128 => u128, visit_u128;
+        deserialize_f32 => f32, visit_f32;
+        deserialize_f64 => f64, visit_f64;
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.value.text() {
+            Some(text) => match text.trim() {
+                "true" | "1" => visitor.visit_bool(true),
+                "false" | "0" => visitor.visit_bool(false),
+                _ => Err(de::Error::invalid_value(
+                    de::Unexpected::Str(text),
+                    &visitor,
+                )),
+            },
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.value {
+            Value::Bool(v) => visitor.visit_string(v.to_string()),
+            Value::I64(v) => visitor.visit_string(v.to_string()),
+            Value::U64(v) => visitor.visit_string(v.to_string()),
+            Value::F64(v) => visitor.visit_string(v.to_string()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        // An empty element is how `None` gets written, see `parse_sale_or_empty_string`.
+        match self.value {
+            Value::Unit => visitor.visit_none(),
+            _ if self.value.text() == Some("") => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        let items = match self.value {
+            Value::Seq(items) => items,
+            Value::Unit => Vec::new(),
+            value => vec![value],
+        };
+        visitor.visit_seq(ValueSeqAccess::new(items, self.element))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        let entries = match self.value {
+            Value::Map(entries) => entries,
+            Value::Unit => Vec::new(),
+            Value::Str(text) if text.is_empty() => Vec::new(),
+            // An element with only text can still fill a struct's `$text` field.
+            Value::Str(text) => vec![(TEXT_KEY.to_string(), Value::Str(text))],
+            value => {
+                return ValueDeserializer::new(value, &self.element).deserialize_any(visitor);
+            }
+        };
+        visitor.visit_map(ValueMapAccess::new(entries))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        if let Some(text) = self.value.text() {
+            return visitor.visit_enum(text.to_string().into_deserializer());
+        }
+        match self.value {
+            Value::Map(mut entries) if entries.len() == 1 => {
+                let (variant, value) = entries.remove(0);
+                visitor.visit_enum(ValueEnumAccess { variant, value })
+            }
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &visitor)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf
+    }
+}
+
+struct ValueSeqAccess {
+    items: std::vec::IntoIter<Value>,
+    element: String,
+}
+
+impl ValueSeqAccess {
+    fn new(items: Vec<Value>, element: String) -> Self {
+        Self {
+            items: items.into_iter(),
+            element,
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for ValueSeqAccess {
+    type Error = ValueError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, ValueError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.items
+            .next()
+            .map(|item| seed.deserialize(ValueDeserializer::new(item, &self.element)))
+            .transpose()
+    }
+}
+
+struct ValueMapAccess {
+    entries: std::vec::IntoIter<(String, Value)>,
+    pending: Option<(String, Value)>,
+}
+
+impl ValueMapAccess {
+    fn new(entries: Vec<(String, Value)>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+            pending: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for ValueMapAccess {
+    type Error = ValueError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, ValueError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let out = seed.deserialize(IntoDeserializer::<ValueError>::into_deserializer(
+            key.as_str(),
+        ))?;
+        self.pending = Some((key, value));
+        Ok(Some(out))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, ValueError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (key, value) = self
+            .pending
+            .take()
+            .ok_or_else(|| <ValueError as de::Error>::custom("value requested before key"))?;
+        seed.deserialize(ValueDeserializer::new(value, &key))
+            .map_err(|err| err.in_element(&key))
+    }
+}
+
+struct ValueEnumAccess {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> EnumAccess<'de> for ValueEnumAccess {
+    type Error = ValueError;
+    type Variant = ValueDeserializer;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), ValueError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(IntoDeserializer::<ValueError>::into_deserializer(
+            self.variant.as_str(),
+        ))?;
+        Ok((variant, ValueDeserializer::new(self.value, &self.variant)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for ValueDeserializer {
+    type Error = ValueError;
+
+    fn unit_variant(self) -> Result<(), ValueError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, ValueError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        self.deserialize_map(visitor)
+    }
+}
+
+thread_local! {
+    static STRICT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with strict reading switched on or off for every flattened enum it
+/// deserializes, restoring the previous setting afterwards.
+///
+/// Leniently, the first variant element is taken and any other element is left to
+/// whoever else reads it, such as [`Extra`](super::extra::Extra). Strictly, every element
+/// the enum is offered must be accounted for: a second variant element is a
+/// [`FieldError::Duplicate`] and any other element a [`FieldError::Unexpected`].
+pub fn with_strict<T>(strict: bool, f: impl FnOnce() -> T) -> T {
+    scoped(&STRICT, strict, f)
+}
+
+/// Whether the current thread reads flattened enums strictly, see [`with_strict`].
+pub fn is_strict() -> bool {
+    STRICT.get()
+}
+
+/// Reads the single `<Variant>` entry of a flattened newtype-variant enum; `variants`
+/// only serves error messages, the caller matches the name. `is_variant` tells the
+/// enum's element names apart from siblings. Used by `#[derive(FlattenedNewtypeEnum)]`.
+pub fn take_variant<'de, D>(
+    deserializer: D,
+    enum_name: &'static str,
+    variants: &'static [&'static str],
+    is_variant: fn(&str) -> bool,
+) -> Result<(String, Value), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(VariantVisitor {
+        enum_name,
+        variants,
+        is_variant,
+    })
+}
+
+/// Reads a map's entries in order, keeping repeated keys apart rather than folding
+/// them into a sequence like [`Value`] does.
+pub fn entries<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, Value)>, D::Error> {
+    struct EntriesVisitor;
+
+    impl<'de> Visitor<'de> for EntriesVisitor {
+        type Value = Vec<(String, Value)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("child elements")
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+            let mut entries = Vec::new();
+            while let Some(entry) = map.next_entry::<String, Value>()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(EntriesVisitor)
+}
+
+/// Error for an element that names none of an enum's variants.
+pub fn unknown_variant<E: de::Error>(enum_name: &str, element: String) -> E {
+    FieldError::UnknownVariant {
+        enum_name: enum_name.to_string(),
+        element,
+    }
+    .raise()
+}
+
+struct VariantVisitor {
+    enum_name: &'static str,
+    variants: &'static [&'static str],
+    is_variant: fn(&str) -> bool,
+}
+
+impl<'de> Visitor<'de> for VariantVisitor {
+    type Value = (String, Value);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "one of the {} elements {:?}",
+            self.enum_name, self.variants
+        )
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let missing = || FieldError::Missing(self.variants.join(" or ")).raise();
+        let strict = is_strict();
+        let mut found: Option<(String, Value)> = None;
+        let mut unknown: Option<String> = None;
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            if !(self.is_variant)(&key) {
+                // Reported once we know whether there is a variant at all.
+                unknown.get_or_insert(key);
+                continue;
+            }
+            // The map is read to the end either way, which quick-xml expects of an
+            // element's content.
+            match &found {
+                None => found = Some((key, value)),
+                Some((first, _)) if strict => {
+                    return Err(FieldError::Duplicate {
+                        first: first.clone(),
+                        second: key,
+                    }
+                    .raise());
+                }
+                Some(_) => {}
+            }
+        }
+        match (found, unknown) {
+            (Some(_), Some(element)) if strict => Err(FieldError::Unexpected(element).raise()),
+            (Some(found), _) => Ok(found),
+            (None, Some(element)) => Err(unknown_variant(self.enum_name, element)),
+            (None, None) => Err(missing()),
+        }
+    }
+}
+
+/// Implemented by `#[derive(FlattenedNewtypeEnum)]`.
+pub trait FlattenedEnum {
+    /// Whether `element` names one of the enum's variants, aliases included.
+    fn is_variant(element: &str) -> bool;
+}
+
+/// A newtype-variant enum taken apart into its variant's element name and content,
+/// so it can be written in other shapes than `<Variant>content</Variant>`, see
+/// [`tagged`](super::tagged). Implemented by `#[derive(FlattenedNewtypeEnum)]`.
+pub trait NewtypeEnum: Sized {
+    /// Element name the variant is written under.
+    fn variant_name(&self) -> &'static str;
+
+    /// Writes the variant's content alone.
+    fn serialize_content<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    /// Reads the variant named `element`, aliases included, from its content.
+    fn from_variant(element: String, value: Value) -> Result<Self, ValueError>;
+}
+
+/// Inner type of a `#[flattened(other)]` variant, which picks its element name at
+/// runtime instead of having one fixed by the enum.
+pub trait OtherVariant: Sized {
+    /// Element name this value is written under.
+    fn element_name(&self) -> &'static str;
+
+    /// Writes the element's content.
+    fn serialize_content<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    /// Reads a value written under `element`, or `None` if the name is not one of ours.
+    fn from_element(element: &str, value: Value) -> Option<Result<Self, ValueError>>;
+
+    /// Whether `element` is one of ours, without reading a value.
+    fn is_element(element: &str) -> bool {
+        Self::from_element(element, Value::Unit).is_some()
+    }
+}
+
+/// Serializes an [`OtherVariant`]'s content. Used by `#[derive(FlattenedNewtypeEnum)]`.
+pub struct OtherContent<'a, T>(pub &'a T);
+
+impl<T: OtherVariant> Serialize for OtherContent<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_content(serializer)
+    }
+}
@@ -0,0 +1,200 @@
+//! Reading and writing products as JSON, in the same shape as the XML documents:
+//! `{"Name": "Yo-yo", "Dollars": 6.0, "Sale": {"AmountOff": {"Dollars": 1.5}}}`.
+//!
+//! The shape comes from the same `Serialize`/`Deserialize` impls as [`xml`](super::xml),
+//! so the currency is flattened into the product under its element name. Amounts
+//! are written as JSON numbers, or as strings when too large for an exact `f64`,
+//! and read from numbers or strings.
+//!
+//! One place differs from XML: a percentage off is written as a bare number,
+//! `"Sale": 25.5`, the shape JSON documents had before sales had kinds, rather than
+//! as `{"PercentOff": 25.5}`. Both are read.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use derive_more::{Display, From};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::error::Category;
+
+use super::{
+    Product,
+    types::{
+        CurrencyNaming, FieldError, FieldErrorVariants, ParseNumberError, amount,
+        describe_duplicate, iso4217, with_bare_percent,
+    },
+};
+
+/// Everything that can go wrong while reading or writing a [`Product`] as JSON.
+#[derive(Debug, Display, From)]
+pub enum JsonError {
+    #[display("I/O error: {_0}")]
+    Io(std::io::Error),
+    #[display("malformed JSON at line {line}, column {column}: {source}")]
+    #[from(skip)]
+    Syntax {
+        line: usize,
+        column: usize,
+        source: serde_json::Error,
+    },
+    #[display("unknown currency field {_0:?}")]
+    #[from(skip)]
+    UnknownCurrency(String),
+    #[display("invalid number {value:?} in {field:?}: {source}")]
+    #[from(skip)]
+    InvalidNumber {
+        field: String,
+        value: String,
+        source: ParseNumberError,
+    },
+    #[display("missing field {_0:?}")]
+    #[from(skip)]
+    MissingField(String),
+    #[display("{}", describe_duplicate("field", first, second))]
+    #[from(skip)]
+    DuplicateField { first: String, second: String },
+    #[display("unexpected field {_0:?}")]
+    #[from(skip)]
+    UnexpectedField(String),
+    #[display("failed to deserialize: {_0}")]
+    #[from(skip)]
+    Deserialize(serde_json::Error),
+    #[display("failed to serialize: {_0}")]
+    #[from(skip)]
+    Serialize(serde_json::Error),
+}
+
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Syntax { source, .. } => Some(source),
+            Self::InvalidNumber { source, .. } => Some(source),
+            Self::Deserialize(err) | Self::Serialize(err) => Some(err),
+            Self::UnknownCurrency(_)
+            | Self::MissingField(_)
+            | Self::DuplicateField { .. }
+            | Self::UnexpectedField(_) => None,
+        }
+    }
+}
+
+impl JsonError {
+    /// Classifies a deserialization failure by the [`FieldError`] raised for it, if
+    /// any, or else by serde_json's own category.
+    fn from_de((err, field): (serde_json::Error, Option<FieldError>)) -> Self {
+        match err.classify() {
+            Category::Io => Self::Io(err.into()),
+            Category::Syntax | Category::Eof => Self::Syntax {
+                line: err.line(),
+                column: err.column(),
+                source: err,
+            },
+            Category::Data => FieldError::classify(field, || Self::Deserialize(err)),
+        }
+    }
+}
+
+impl FieldErrorVariants for JsonError {
+    fn unknown_currency(field: String) -> Self {
+        Self::UnknownCurrency(field)
+    }
+
+    fn invalid_number(field: String, value: String, source: ParseNumberError) -> Self {
+        Self::InvalidNumber {
+            field,
+            value,
+            source,
+        }
+    }
+
+    fn missing(field: String) -> Self {
+        Self::MissingField(field)
+    }
+
+    fn duplicate(first: String, second: String) -> Self {
+        Self::DuplicateField { first, second }
+    }
+
+    fn unexpected(field: String) -> Self {
+        Self::UnexpectedField(field)
+    }
+}
+
+/// How documents are written by [`to_json_writer_with`] and [`to_json_string_with`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteOptions {
+    /// Indent nested values by two spaces instead of writing everything on one line.
+    pub pretty: bool,
+    /// Whether USD and EUR are written as `"Dollars"`/`"Euros"` or by their ISO code.
+    pub currency_naming: CurrencyNaming,
+}
+
+/// Deserializes a `T` from a JSON document held in memory.
+pub fn from_json_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T, JsonError> {
+    FieldError::catch(|| serde_json::from_str(input)).map_err(JsonError::from_de)
+}
+
+/// Deserializes a `T` from a JSON document read from `reader`.
+pub fn from_json_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, JsonError> {
+    FieldError::catch(|| serde_json::from_reader(reader)).map_err(JsonError::from_de)
+}
+
+/// Serializes `obj` as a JSON document into `writer`.
+pub fn to_json_writer<W: Write, T: Serialize>(writer: W, obj: &T) -> Result<(), JsonError> {
+    to_json_writer_with(writer, obj, &WriteOptions::default())
+}
+
+pub fn to_json_writer_with<W: Write, T: Serialize>(
+    writer: W,
+    obj: &T,
+    options: &WriteOptions,
+) -> Result<(), JsonError> {
+    iso4217::with_naming(options.currency_naming, || {
+        amount::with_numbers(|| {
+            with_bare_percent(|| {
+                if options.pretty {
+                    serde_json::to_writer_pretty(writer, obj)
+                } else {
+                    serde_json::to_writer(writer, obj)
+                }
+            })
+        })
+    })
+    .map_err(|err| match err.classify() {
+        Category::Io => JsonError::Io(err.into()),
+        _ => JsonError::Serialize(err),
+    })
+}
+
+/// Serializes `obj` as a JSON document held in memory.
+pub fn to_json_string<T: Serialize>(obj: &T) -> Result<String, JsonError> {
+    to_json_string_with(obj, &WriteOptions::default())
+}
+
+pub fn to_json_string_with<T: Serialize>(
+    obj: &T,
+    options: &WriteOptions,
+) -> Result<String, JsonError> {
+    let mut out = Vec::new();
+    to_json_writer_with(&mut out, obj, options)?;
+    Ok(String::from_utf8(out).expect("serde_json writes UTF-8"))
+}
+
+pub fn from_json_file(file_path: impl Into<PathBuf>) -> Result<Product, JsonError> {
+    let source = File::open(file_path.into())?;
+    from_json_reader(BufReader::new(source))
+}
+
+pub fn to_json_file(file_path: impl Into<PathBuf>, obj: &Product) -> Result<File, JsonError> {
+    let file = File::create(file_path.into())?;
+    let mut writer = BufWriter::new(&file);
+    to_json_writer(&mut writer, obj)?;
+    writer.flush()?;
+    drop(writer);
+
+    Ok(file)
+}
@@ -0,0 +1,198 @@
+//! JSON Schema (draft 2020-12) for the documents read and written by
+//! [`json`](super::json).
+//!
+//! The schemas follow what deserialization accepts rather than only what
+//! serialization writes:
+//! - A [`Currency`](super::types::Currency) is an object with a legacy name
+//!   (`Dollars`, `Euros`) or an ISO 4217 code as key. Flattened into a product,
+//!   such a key sits beside `Name` and `Sale`, or a `Price` key holds the
+//!   [attribute encoding](super::types::currency_attribute),
+//!   `{"@currency": "USD", "$text": "6.50"}`, or a nested currency object.
+//! - Amounts are numbers or decimal strings. The string pattern enforces the
+//!   currency's fraction digits; for numbers that is left to the reader, since
+//!   `multipleOf` on floats is unreliable across validators.
+//! - `Sale` may be missing, `null`, empty (`""` or `{}`), a legacy bare percentage
+//!   or an object holding one of its kinds.
+//!
+//! By default the schemas describe lenient reading: keys nothing reads are allowed,
+//! as they end up in [`extra`](super::Product::extra), and a second currency or
+//! sale kind is allowed, as the first one is taken. With
+//! [`SchemaOptions::strict`] they describe reading under
+//! [`flatten::with_strict`](super::flatten::with_strict) instead, which rejects both.
+
+use std::collections::BTreeSet;
+
+use serde_json::{Map, Value, json};
+
+use super::{
+    flatten::TEXT_KEY,
+    types::{
+        LEGACY_NAMES,
+        currency_attribute::{ATTRIBUTE, ELEMENT},
+        iso4217::CURRENCIES,
+    },
+};
+
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Which reading the schemas describe.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SchemaOptions {
+    /// Reject unknown keys and a second currency or sale kind, like strict reading.
+    pub strict: bool,
+}
+
+/// Schema for a single product document.
+pub fn product_schema() -> Value {
+    product_schema_with(&SchemaOptions::default())
+}
+
+pub fn product_schema_with(options: &SchemaOptions) -> Value {
+    let mut product = json!({
+        "type": "object",
+        "properties": {
+            "Name": { "type": "string" },
+            "Sale": { "$ref": "#/$defs/Sale" },
+        },
+        "required": ["Name"],
+        "allOf": [{ "$ref": "#/$defs/FlattenedCurrency" }],
+    });
+    if options.strict {
+        product["unevaluatedProperties"] = false.into();
+    }
+    root("Product", product, options)
+}
+
+/// Schema for a currency amount on its own, e.g. `{"Dollars": 6.0}`.
+pub fn currency_schema() -> Value {
+    currency_schema_with(&SchemaOptions::default())
+}
+
+pub fn currency_schema_with(options: &SchemaOptions) -> Value {
+    root("Currency", json!({ "$ref": "#/$defs/Currency" }), options)
+}
+
+/// Schema for the value of a product's `Sale` key.
+pub fn sale_schema() -> Value {
+    sale_schema_with(&SchemaOptions::default())
+}
+
+pub fn sale_schema_with(options: &SchemaOptions) -> Value {
+    root("Sale", json!({ "$ref": "#/$defs/Sale" }), options)
+}
+
+/// Completes `schema` with the dialect, a title and every definition it may refer to.
+fn root(title: &str, schema: Value, options: &SchemaOptions) -> Value {
+    let Value::Object(mut schema) = schema else {
+        unreachable!("schemas are objects");
+    };
+    let mut root = Map::new();
+    root.insert("$schema".into(), DIALECT.into());
+    root.insert("title".into(), title.into());
+    root.append(&mut schema);
+    root.insert("$defs".into(), definitions(options));
+    Value::Object(root)
+}
+
+/// Every key a currency may be written under, with its number of fraction digits.
+fn currency_keys() -> impl Iterator<Item = (&'static str, u8)> {
+    let legacy = LEGACY_NAMES.iter().map(|(legacy, alpha)| {
+        let code = CURRENCIES
+            .iter()
+            .find(|code| code.alpha == *alpha)
+            .expect("legacy currencies are listed");
+        (*legacy, code.minor_units)
+    });
+    legacy.chain(CURRENCIES.iter().map(|code| (code.alpha, code.minor_units)))
+}
+
+fn decimal_name(scale: u8) -> String {
+    format!("Decimal{scale}")
+}
+
+fn decimal_ref(scale: u8) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", decimal_name(scale)) })
+}
+
+/// A number, or a string `Amount` parses with at most `scale` significant fraction digits.
+fn decimal(scale: u8) -> Value {
+    let fraction = if scale == 0 {
+        r"\d*\.0+".to_string()
+    } else {
+        format!(r"\d*\.\d{{1,{scale}}}0*")
+    };
+    json!({
+        "type": ["number", "string"],
+        "pattern": format!(r"^\s*[+-]?(\d+\.?|{fraction})\s*$"),
+    })
+}
+
+/// An object holding `key`, and strictly nothing else.
+fn variant(key: &str, schema: Value, options: &SchemaOptions) -> Value {
+    let mut variant = json!({
+        "type": "object",
+        "properties": { (key): schema },
+        "required": [key],
+    });
+    if options.strict {
+        variant["additionalProperties"] = false.into();
+    }
+    variant
+}
+
+/// Exactly one of `schemas` when strict, at least one when lenient.
+fn one_of(schemas: Vec<Value>, options: &SchemaOptions) -> Value {
+    let keyword = if options.strict { "oneOf" } else { "anyOf" };
+    json!({ (keyword): schemas })
+}
+
+fn definitions(options: &SchemaOptions) -> Value {
+    let currency = currency_keys()
+        .map(|(key, scale)| variant(key, decimal_ref(scale), options))
+        .collect();
+    let attributed = currency_keys()
+        .map(|(key, scale)| {
+            let mut price = variant(ATTRIBUTE, json!({ "const": key }), options);
+            price["properties"][TEXT_KEY] = decimal_ref(scale);
+            price["required"] = json!([ATTRIBUTE, TEXT_KEY]);
+            price
+        })
+        .chain([json!({ "$ref": "#/$defs/Currency" })])
+        .collect();
+    // Beside other keys, so `unevaluatedProperties` on the product rejects extras.
+    let flattened = currency_keys()
+        .map(|(key, scale)| (key, decimal_ref(scale)))
+        .chain([(ELEMENT, json!({ "$ref": "#/$defs/Price" }))])
+        .map(|(key, schema)| {
+            json!({
+                "properties": { (key): schema },
+                "required": [key],
+            })
+        })
+        .collect();
+    let sale = [
+        json!({ "type": "null" }),
+        json!({ "const": "" }),
+        json!({ "type": "object", "maxProperties": 0 }),
+        decimal_ref(2),
+    ];
+    let sale_kinds = [
+        ("PercentOff", decimal_ref(2)),
+        ("AmountOff", json!({ "$ref": "#/$defs/Currency" })),
+        ("SalePrice", json!({ "$ref": "#/$defs/Currency" })),
+    ]
+    .into_iter()
+    .map(|(key, schema)| variant(key, schema, options));
+
+    let mut defs = Map::new();
+    defs.insert("Currency".into(), one_of(currency, options));
+    defs.insert("Price".into(), one_of(attributed, options));
+    defs.insert("FlattenedCurrency".into(), one_of(flattened, options));
+    let sale = sale.into_iter().chain(sale_kinds).collect();
+    defs.insert("Sale".into(), one_of(sale, options));
+    let scales: BTreeSet<u8> = currency_keys().map(|(_, scale)| scale).collect();
+    for scale in scales {
+        defs.insert(decimal_name(scale), decimal(scale));
+    }
+    Value::Object(defs)
+}
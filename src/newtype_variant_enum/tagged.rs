@@ -0,0 +1,306 @@
+//! Serialize/deserialize adapters for newtype enums written with the variant name
+//! as a tag beside their content, rather than as the element around it:
+//!
+//! ```text
+//! adjacently tagged: <Price><Kind>Dollars</Kind><Amount>6.00</Amount></Price>
+//!                    {"type": "Dollars", "value": 6.0}
+//! internally tagged: <Sale><Kind>AmountOff</Kind><Dollars>1.00</Dollars></Sale>
+//!                    <Sale><Kind>PercentOff</Kind>10.00</Sale>
+//! ```
+//!
+//! They work on any `#[derive(FlattenedNewtypeEnum)]` type through [`NewtypeEnum`],
+//! with the tag and content names picked by a [`TagNames`] type:
+//!
+//! ```text
+//! #[serde(
+//!     serialize_with = "tagged::serialize_adjacent::<KindAmount, _, _>",
+//!     deserialize_with = "tagged::deserialize_adjacent::<KindAmount, _, _>"
+//! )]
+//! price: Currency,
+//! ```
+//!
+//! Reading buffers the element in a [`Value`], as the flattened shape does, so the
+//! content can still be parsed from the text quick-xml hands over. Internally tagged
+//! content that is not a map, such as an amount, becomes the element's text.
+
+use serde::{
+    Deserializer, Serialize, Serializer, de,
+    ser::{self, Impossible, SerializeMap, SerializeStruct},
+};
+
+use super::{
+    flatten::{self, NewtypeEnum, TEXT_KEY, Value, ValueError},
+    types::FieldError,
+};
+
+/// Names of the keys a tagged newtype enum is written under.
+pub trait TagNames {
+    /// Key holding the variant name.
+    const TAG: &'static str;
+    /// Key holding the content, in the adjacently tagged shape.
+    const CONTENT: &'static str;
+}
+
+/// `<Kind>Dollars</Kind><Amount>6.00</Amount>`.
+pub struct KindAmount;
+
+impl TagNames for KindAmount {
+    const TAG: &'static str = "Kind";
+    const CONTENT: &'static str = "Amount";
+}
+
+/// `{"type": "Dollars", "value": 6.0}`.
+pub struct TypeValue;
+
+impl TagNames for TypeValue {
+    const TAG: &'static str = "type";
+    const CONTENT: &'static str = "value";
+}
+
+/// Writes `value` as its tag followed by its content under [`TagNames::CONTENT`].
+pub fn serialize_adjacent<N, T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    N: TagNames,
+    T: NewtypeEnum,
+    S: Serializer,
+{
+    struct Content<'a, T>(&'a T);
+    impl<T: NewtypeEnum> Serialize for Content<'_, T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize_content(serializer)
+        }
+    }
+
+    let mut map = serializer.serialize_map(Some(2))?;
+    map.serialize_entry(N::TAG, value.variant_name())?;
+    map.serialize_entry(N::CONTENT, &Content(value))?;
+    map.end()
+}
+
+/// Reads what [`serialize_adjacent`] writes. A missing content is read as empty.
+pub fn deserialize_adjacent<'de, N, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    N: TagNames,
+    T: NewtypeEnum,
+    D: Deserializer<'de>,
+{
+    let mut entries = flatten::entries(deserializer)?;
+    let tag = take_tag::<N, D::Error>(&mut entries)?;
+    let content = take(&mut entries, N::CONTENT).unwrap_or(Value::Unit);
+    if flatten::is_strict()
+        && let Some((key, _)) = entries.first()
+    {
+        return Err(FieldError::Unexpected(key.clone()).raise());
+    }
+    T::from_variant(tag, content).map_err(ValueError::into_de)
+}
+
+/// Writes `value` as its tag followed by the entries of its content, or by its
+/// content as text when that is not a map or struct.
+pub fn serialize_internal<N, T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    N: TagNames,
+    T: NewtypeEnum,
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry(N::TAG, value.variant_name())?;
+    value.serialize_content(MergeSerializer(&mut map))?;
+    map.end()
+}
+
+/// Reads what [`serialize_internal`] writes.
+pub fn deserialize_internal<'de, N, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    N: TagNames,
+    T: NewtypeEnum,
+    D: Deserializer<'de>,
+{
+    let mut entries = flatten::entries(deserializer)?;
+    let tag = take_tag::<N, D::Error>(&mut entries)?;
+    let content = match entries.as_slice() {
+        [] => Value::Unit,
+        [(key, _)] if key == TEXT_KEY => entries.remove(0).1,
+        _ => Value::Map(entries),
+    };
+    T::from_variant(tag, content).map_err(ValueError::into_de)
+}
+
+fn take(entries: &mut Vec<(String, Value)>, key: &str) -> Option<Value> {
+    let index = entries.iter().position(|(k, _)| k == key)?;
+    Some(entries.remove(index).1)
+}
+
+fn take_tag<N: TagNames, E: de::Error>(entries: &mut Vec<(String, Value)>) -> Result<String, E> {
+    take(entries, N::TAG)
+        .ok_or_else(|| FieldError::Missing(N::TAG.to_string()).raise())?
+        .deserialize_into(N::TAG)
+}
+
+/// Serializes a value's entries into a map that is already open, and anything
+/// that is not a map or struct as its `$text` entry.
+struct MergeSerializer<'a, M>(&'a mut M);
+
+macro_rules! merge_as_text {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<(), M::Error> {
+                self.0.serialize_entry(TEXT_KEY, &v)
+            }
+        )*
+    };
+}
+
+impl<'a, M: SerializeMap> Serializer for MergeSerializer<'a, M> {
+    type Ok = ();
+    type Error = M::Error;
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    merge_as_text! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+    }
+
+    fn serialize_none(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), M::Error> {
+        self.0.serialize_entry(TEXT_KEY, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        self.0.serialize_entry(variant, value)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, M::Error> {
+        Err(unmergeable())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, M::Error> {
+        Err(unmergeable())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, M::Error> {
+        Err(unmergeable())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, M::Error> {
+        Err(unmergeable())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, M::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, M::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, M::Error> {
+        Err(unmergeable())
+    }
+}
+
+fn unmergeable<E: ser::Error>() -> E {
+    E::custom("internally tagged content must be a map, a struct or a scalar")
+}
+
+impl<M: SerializeMap> SerializeMap for MergeSerializer<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), M::Error> {
+        self.0.serialize_key(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), M::Error> {
+        self.0.serialize_value(value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+impl<M: SerializeMap> SerializeStruct for MergeSerializer<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        self.0.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Reading and writing products as TOML, for hand-edited fixtures:
+//!
+//! ```toml
+//! Name = "Yo-yo"
+//! Dollars = 6.0
+//!
+//! [Sale]
+//! PercentOff = 25.5
+//! ```
+//!
+//! The currency key sits at the top level, as in [`json`](super::json), and comes
+//! from the same `Serialize`/`Deserialize` impls. TOML has no null, so a product
+//! without a sale simply has no `Sale` key.
+
+use std::{fs, path::PathBuf};
+
+use derive_more::{Display, From};
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{
+    Product,
+    types::{
+        CurrencyNaming, FieldError, FieldErrorVariants, ParseNumberError, amount,
+        describe_duplicate, iso4217,
+    },
+};
+
+/// Everything that can go wrong while reading or writing a [`Product`] as TOML.
+#[derive(Debug, Display, From)]
+pub enum TomlError {
+    #[display("I/O error: {_0}")]
+    Io(std::io::Error),
+    #[display("malformed TOML at line {line}, column {column}: {}", source.message())]
+    #[from(skip)]
+    Syntax {
+        line: usize,
+        column: usize,
+        source: ::toml::de::Error,
+    },
+    #[display("unknown currency key {_0:?}")]
+    #[from(skip)]
+    UnknownCurrency(String),
+    #[display("invalid number {value:?} in {key:?}: {source}")]
+    #[from(skip)]
+    InvalidNumber {
+        key: String,
+        value: String,
+        source: ParseNumberError,
+    },
+    #[display("missing key {_0:?}")]
+    #[from(skip)]
+    MissingKey(String),
+    #[display("{}", describe_duplicate("key", first, second))]
+    #[from(skip)]
+    DuplicateKey { first: String, second: String },
+    #[display("unexpected key {_0:?}")]
+    #[from(skip)]
+    UnexpectedKey(String),
+    #[display("failed to deserialize: {_0}")]
+    #[from(skip)]
+    Deserialize(::toml::de::Error),
+    #[display("failed to serialize: {_0}")]
+    Serialize(::toml::ser::Error),
+}
+
+impl std::error::Error for TomlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Syntax { source, .. } => Some(source),
+            Self::InvalidNumber { source, .. } => Some(source),
+            Self::Deserialize(err) => Some(err),
+            Self::Serialize(err) => Some(err),
+            Self::UnknownCurrency(_)
+            | Self::MissingKey(_)
+            | Self::DuplicateKey { .. }
+            | Self::UnexpectedKey(_) => None,
+        }
+    }
+}
+
+impl TomlError {
+    /// Classifies a deserialization failure by the [`FieldError`] raised for it, if
+    /// any. The toml crate does not tell syntax errors apart, so a failure is one if
+    /// `input` is not a TOML table at all.
+    fn from_de(input: &str, (err, field): (::toml::de::Error, Option<FieldError>)) -> Self {
+        FieldError::classify(field, || {
+            match (input.parse::<::toml::Table>(), err.span()) {
+                (Err(_), Some(span)) => {
+                    let before = &input[..span.start.min(input.len())];
+                    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+                    Self::Syntax {
+                        line: before.matches('\n').count() + 1,
+                        column: before[line_start..].chars().count() + 1,
+                        source: err,
+                    }
+                }
+                _ => Self::Deserialize(err),
+            }
+        })
+    }
+}
+
+impl FieldErrorVariants for TomlError {
+    fn unknown_currency(key: String) -> Self {
+        Self::UnknownCurrency(key)
+    }
+
+    fn invalid_number(key: String, value: String, source: ParseNumberError) -> Self {
+        Self::InvalidNumber { key, value, source }
+    }
+
+    fn missing(key: String) -> Self {
+        Self::MissingKey(key)
+    }
+
+    fn duplicate(first: String, second: String) -> Self {
+        Self::DuplicateKey { first, second }
+    }
+
+    fn unexpected(key: String) -> Self {
+        Self::UnexpectedKey(key)
+    }
+}
+
+/// How documents are written by [`to_toml_string_with`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteOptions {
+    /// Whether USD and EUR are written as `Dollars`/`Euros` or by their ISO code.
+    pub currency_naming: CurrencyNaming,
+}
+
+/// Deserializes a `T` from a TOML document held in memory.
+pub fn from_toml_str<T: DeserializeOwned>(input: &str) -> Result<T, TomlError> {
+    FieldError::catch(|| ::toml::from_str(input)).map_err(|err| TomlError::from_de(input, err))
+}
+
+/// Serializes `obj` as a TOML document held in memory.
+pub fn to_toml_string<T: Serialize>(obj: &T) -> Result<String, TomlError> {
+    to_toml_string_with(obj, &WriteOptions::default())
+}
+
+pub fn to_toml_string_with<T: Serialize>(
+    obj: &T,
+    options: &WriteOptions,
+) -> Result<String, TomlError> {
+    let out = iso4217::with_naming(options.currency_naming, || {
+        amount::with_numbers(|| ::toml::to_string(obj))
+    })?;
+    Ok(out)
+}
+
+pub fn from_toml_file(file_path: impl Into<PathBuf>) -> Result<Product, TomlError> {
+    from_toml_str(&fs::read_to_string(file_path.into())?)
+}
+
+pub fn to_toml_file(file_path: impl Into<PathBuf>, obj: &Product) -> Result<(), TomlError> {
+    fs::write(file_path.into(), to_toml_string(obj)?)?;
+    Ok(())
+}
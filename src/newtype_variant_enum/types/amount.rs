@@ -0,0 +1,232 @@
+use std::{cell::Cell, fmt, str::FromStr};
+
+use derive_more::Display;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+
+use super::{FieldError, scoped};
+
+/// Exact decimal amount stored as integer minor units, `SCALE` fraction digits each.
+///
+/// `Amount<2>` holds cents: `"6.5"` parses to 650 minor units and is written back as
+/// the canonical `"6.50"`. Parsing rejects more fraction digits than `SCALE` rather
+/// than rounding, so a value never silently changes on a round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Amount<const SCALE: u8> {
+    minor: i64,
+}
+
+impl<const SCALE: u8> Amount<SCALE> {
+    pub const ZERO: Self = Self { minor: 0 };
+
+    pub const fn from_minor(minor: i64) -> Self {
+        Self { minor }
+    }
+
+    /// Amount of whole units, e.g. `Amount::<2>::from_major(6)` is `6.00`.
+    ///
+    /// # Panics
+    ///
+    /// If the amount does not fit in `i64` minor units, about `9.2e18 / 10^SCALE`
+    /// whole units; see [`Self::checked_from_major`].
+    pub const fn from_major(major: i64) -> Self {
+        match Self::checked_from_major(major) {
+            Some(amount) => amount,
+            None => panic!("amount too large"),
+        }
+    }
+
+    /// Amount of whole units, or `None` if it does not fit in `i64` minor units.
+    pub const fn checked_from_major(major: i64) -> Option<Self> {
+        let Some(unit) = 10i64.checked_pow(SCALE as u32) else {
+            return None;
+        };
+        match major.checked_mul(unit) {
+            Some(minor) => Some(Self { minor }),
+            None => None,
+        }
+    }
+
+    pub const fn minor_units(self) -> i64 {
+        self.minor
+    }
+
+    /// Name used for this type in error messages, e.g. `decimal(2)`.
+    pub fn type_name() -> String {
+        format!("decimal({SCALE})")
+    }
+}
+
+impl<const SCALE: u8> fmt::Display for Amount<SCALE> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_minor_units(f, self.minor, SCALE)
+    }
+}
+
+/// Writes `minor` units of `10^-scale` as a canonical decimal with `scale` fraction digits.
+pub(crate) fn fmt_minor_units(f: &mut fmt::Formatter, minor: i64, scale: u8) -> fmt::Result {
+    let sign = if minor < 0 { "-" } else { "" };
+    let minor = minor.unsigned_abs();
+    if scale == 0 {
+        return write!(f, "{sign}{minor}");
+    }
+    let unit = 10u64.pow(scale as u32);
+    let (major, fraction) = (minor / unit, minor % unit);
+    write!(
+        f,
+        "{sign}{major}.{fraction:0width$}",
+        width = scale as usize
+    )
+}
+
+impl<const SCALE: u8> FromStr for Amount<SCALE> {
+    type Err = ParseAmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_minor_units(s, SCALE).map(Self::from_minor)
+    }
+}
+
+impl<const SCALE: u8> Serialize for Amount<SCALE> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_minor_units(serializer, self.minor, SCALE)
+    }
+}
+
+thread_local! {
+    static AS_NUMBERS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with amounts serialized as numbers instead of canonical decimal text.
+///
+/// Formats with a native number type (JSON) set this, see `iso4217::with_naming`.
+/// The number is the nearest `f64`, written only when its shortest form reads back
+/// as the same amount. Larger or finer amounts, from about 2^53 minor units on,
+/// keep their decimal text so nothing is lost.
+pub(crate) fn with_numbers<T>(f: impl FnOnce() -> T) -> T {
+    scoped(&AS_NUMBERS, true, f)
+}
+
+/// Serializes `minor` units of `10^-scale` as text or, inside [`with_numbers`], as a number.
+pub(crate) fn serialize_minor_units<S: Serializer>(
+    serializer: S,
+    minor: i64,
+    scale: u8,
+) -> Result<S::Ok, S::Error> {
+    struct Decimal(i64, u8);
+    impl fmt::Display for Decimal {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt_minor_units(f, self.0, self.1)
+        }
+    }
+
+    let number = minor as f64 / 10f64.powi(scale.into());
+    if AS_NUMBERS.get() && parse_minor_units(&number.to_string(), scale) == Ok(minor) {
+        serializer.serialize_f64(number)
+    } else {
+        serializer.collect_str(&Decimal(minor, scale))
+    }
+}
+
+impl<'de, const SCALE: u8> Deserialize<'de> for Amount<SCALE> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(AmountVisitor::<SCALE>)
+    }
+}
+
+struct AmountVisitor<const SCALE: u8>;
+
+impl<const SCALE: u8> AmountVisitor<SCALE> {
+    fn parse<E: de::Error>(text: &str) -> Result<Amount<SCALE>, E> {
+        text.parse().map_err(|err: ParseAmountError| {
+            FieldError::InvalidNumber {
+                element: String::new(),
+                value: text.to_string(),
+                ty: Amount::<SCALE>::type_name(),
+                source: err.into(),
+            }
+            .raise()
+        })
+    }
+}
+
+impl<'de, const SCALE: u8> Visitor<'de> for AmountVisitor<SCALE> {
+    type Value = Amount<SCALE>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a decimal number with at most {SCALE} fraction digits")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Self::parse(v)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Self::parse(&v.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Self::parse(&v.to_string())
+    }
+
+    // `f64`'s `Display` is the shortest string that reads back as the same float,
+    // which is the decimal the document's author wrote for any sane amount.
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Self::parse(&v.to_string())
+    }
+}
+
+/// Why text could not be read as an [`Amount`].
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum ParseAmountError {
+    #[display("cannot parse amount from empty string")]
+    Empty,
+    #[display("invalid digit found in amount")]
+    InvalidDigit,
+    #[display("{found} fraction digits given but at most {max} allowed")]
+    TooManyFractionDigits { max: u8, found: usize },
+    #[display("amount too large")]
+    Overflow,
+}
+
+impl std::error::Error for ParseAmountError {}
+
+/// Parses a plain decimal (`-12.34`, no exponent) into units of `10^-scale`.
+pub(crate) fn parse_minor_units(s: &str, scale: u8) -> Result<i64, ParseAmountError> {
+    let s = s.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (major, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if major.is_empty() && fraction.is_empty() {
+        return Err(ParseAmountError::Empty);
+    }
+    if !major
+        .bytes()
+        .chain(fraction.bytes())
+        .all(|b| b.is_ascii_digit())
+    {
+        return Err(ParseAmountError::InvalidDigit);
+    }
+    // Trailing zeros carry no precision, so "6.500" is fine for a scale of 2.
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > scale as usize {
+        return Err(ParseAmountError::TooManyFractionDigits {
+            max: scale,
+            found: fraction.len(),
+        });
+    }
+
+    let mut minor: i64 = 0;
+    let padding = std::iter::repeat_n(b'0', scale as usize - fraction.len());
+    for digit in major.bytes().chain(fraction.bytes()).chain(padding) {
+        minor = minor
+            .checked_mul(10)
+            .and_then(|m| m.checked_add(i64::from(digit - b'0')))
+            .ok_or(ParseAmountError::Overflow)?;
+    }
+    Ok(if negative { -minor } else { minor })
+}
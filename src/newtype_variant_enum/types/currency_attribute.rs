@@ -0,0 +1,176 @@
+//! The attribute encoding of a [`Currency`]: one element named after the field,
+//! with the currency in an attribute, as some partners write prices:
+//!
+//! ```text
+//! <Price currency="USD">6.00</Price>
+//! ```
+//!
+//! rather than the element-per-variant `<Dollars>6.00</Dollars>`. A plain
+//! `Currency` field opts in with `#[serde(with = "currency_attribute")]`. A
+//! product's flattened price reads either encoding and writes the one set by
+//! [`with_encoding`].
+
+use std::cell::Cell;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, ser::SerializeMap};
+
+use super::{Currency, FieldError, Money, scoped};
+use crate::newtype_variant_enum::flatten::{
+    self, FlattenedEnum, OtherContent, TEXT_KEY, Value, ValueDeserializer, ValueError,
+};
+
+/// Element a product's price is written under in the attribute encoding.
+pub const ELEMENT: &str = "Price";
+
+/// Attribute holding the currency, as quick-xml names attributes.
+pub const ATTRIBUTE: &str = "@currency";
+
+/// How a product's price is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurrencyEncoding {
+    /// `<Dollars>6.00</Dollars>`.
+    #[default]
+    Element,
+    /// `<Price currency="USD">6.00</Price>`.
+    Attribute,
+}
+
+thread_local! {
+    static ENCODING: Cell<CurrencyEncoding> = const { Cell::new(CurrencyEncoding::Element) };
+}
+
+/// Runs `f` with `encoding` in effect for every product price it serializes, like
+/// [`iso4217::with_naming`](super::iso4217::with_naming).
+pub fn with_encoding<T>(encoding: CurrencyEncoding, f: impl FnOnce() -> T) -> T {
+    scoped(&ENCODING, encoding, f)
+}
+
+/// The encoding in effect on this thread, see [`with_encoding`].
+pub fn encoding() -> CurrencyEncoding {
+    ENCODING.get()
+}
+
+/// Writes `currency` as the content of an element with a currency attribute. The
+/// code is always the ISO one, whatever the [`CurrencyNaming`](super::CurrencyNaming).
+pub fn serialize<S: Serializer>(currency: &Currency, serializer: S) -> Result<S::Ok, S::Error> {
+    let money = Money::from_minor(currency.code(), currency.minor_units());
+    let mut map = serializer.serialize_map(Some(2))?;
+    map.serialize_entry(ATTRIBUTE, money.code().alpha)?;
+    map.serialize_entry(TEXT_KEY, &OtherContent(&money))?;
+    map.end()
+}
+
+/// Reads a currency written either way inside the field's element: with a currency
+/// attribute, or as a variant element such as `<Price><Dollars>6</Dollars></Price>`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Currency, D::Error> {
+    from_value(Value::deserialize(deserializer)?, "").map_err(ValueError::into_de)
+}
+
+/// Writes a product's flattened price in the encoding set by [`with_encoding`].
+pub fn serialize_flattened<S: Serializer>(
+    currency: &Currency,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match encoding() {
+        CurrencyEncoding::Element => currency.serialize(serializer),
+        CurrencyEncoding::Attribute => {
+            struct Attributed<'a>(&'a Currency);
+            impl Serialize for Attributed<'_> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serialize(self.0, serializer)
+                }
+            }
+
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry(ELEMENT, &Attributed(currency))?;
+            map.end()
+        }
+    }
+}
+
+/// Reads a product's flattened price from either a `<Price>` element or a variant
+/// element among the product's children. Leniently the first of them is taken, as
+/// for any flattened enum; strictly a second one is a [`FieldError::Duplicate`].
+pub fn deserialize_flattened<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Currency, D::Error> {
+    let mut entries = flatten::entries(deserializer)?;
+    let mut prices = entries
+        .iter()
+        .filter(|(key, _)| PriceElements::is_variant(key));
+    let first = prices.next().map(|(key, _)| key.clone());
+    if flatten::is_strict()
+        && let (Some(first), Some((second, _))) = (&first, prices.next())
+    {
+        return Err(FieldError::Duplicate {
+            first: first.clone(),
+            second: second.clone(),
+        }
+        .raise());
+    }
+    if first.as_deref() != Some(ELEMENT) {
+        entries.retain(|(key, _)| key != ELEMENT);
+        return Currency::deserialize(ValueDeserializer::new(Value::Map(entries), ""))
+            .map_err(ValueError::into_de);
+    }
+    let index = entries
+        .iter()
+        .position(|(key, _)| key == ELEMENT)
+        .expect("the first price element is <Price>");
+    let (_, value) = entries.remove(index);
+    if flatten::is_strict()
+        && let Some((key, _)) = entries.first()
+    {
+        return Err(FieldError::Unexpected(key.clone()).raise());
+    }
+    from_value(value, ELEMENT).map_err(ValueError::into_de)
+}
+
+/// Elements a product's price may be read from: a currency variant or `<Price>`.
+pub struct PriceElements;
+
+impl FlattenedEnum for PriceElements {
+    fn is_variant(element: &str) -> bool {
+        element == ELEMENT || Currency::is_variant(element)
+    }
+}
+
+/// Reads the content of the element holding a currency, named `element` in errors.
+fn from_value(value: Value, element: &str) -> Result<Currency, ValueError> {
+    let mut entries = match value {
+        Value::Map(entries) => entries,
+        _ => {
+            return Err(FieldError::Missing(ATTRIBUTE.to_string()).into());
+        }
+    };
+    let Some(index) = entries.iter().position(|(key, _)| key == ATTRIBUTE) else {
+        return Value::Map(entries).deserialize_into(element);
+    };
+    let (_, code) = entries.remove(index);
+    let code: String = code.deserialize_into::<_, ValueError>(ATTRIBUTE)?;
+    let amount: String = match entries.iter().position(|(key, _)| key == TEXT_KEY) {
+        Some(index) => entries
+            .remove(index)
+            .1
+            .deserialize_into::<_, ValueError>(element)?,
+        None => String::new(),
+    };
+    if flatten::is_strict()
+        && let Some((key, _)) = entries.first()
+    {
+        return Err(FieldError::Unexpected(key.clone()).raise());
+    }
+    Currency::parse(&code, &amount)
+        .map_err(|err| match err {
+            FieldError::InvalidNumber {
+                value, ty, source, ..
+            } => FieldError::InvalidNumber {
+                element: element.to_string(),
+                value,
+                ty,
+                source,
+            },
+            err => err,
+        })
+        .map_err(ValueError::from)
+}
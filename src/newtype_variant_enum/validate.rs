@@ -0,0 +1,126 @@
+//! Checks on values that deserialize fine but make no sense as data, such as a
+//! negative price or an empty name.
+//!
+//! [`Validate`] collects every problem it finds rather than stopping at the
+//! first, each tagged with the path of the offending field, e.g. `sale.percent_off`.
+
+use std::fmt;
+
+use super::{
+    Product,
+    types::{Currency, Percent, Sale},
+};
+
+/// A single problem found by [`Validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Dotted path of the offending field, e.g. `sale.amount_off`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// All problems found while validating a value; never empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<Violation>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, violation) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            violation.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Domain rules a deserialized value must satisfy.
+pub trait Validate {
+    /// Appends every violation in `self` to `violations`, with paths below `path`.
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>);
+
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut violations = Vec::new();
+        self.validate_at("", &mut violations);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(violations))
+        }
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{path}.{field}")
+    }
+}
+
+fn violation(violations: &mut Vec<Violation>, path: String, message: impl Into<String>) {
+    violations.push(Violation {
+        path,
+        message: message.into(),
+    });
+}
+
+impl Validate for Product {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        if self.name.trim().is_empty() {
+            violation(violations, join(path, "name"), "must not be empty");
+        }
+        let found = violations.len();
+        self.price.validate_at(&join(path, "price"), violations);
+
+        let Some(sale) = &self.sale else {
+            return;
+        };
+        // Only check the sale against the price once both are sound on their own,
+        // so one mistake is not reported twice.
+        sale.validate_at(&join(path, "sale"), violations);
+        if violations.len() == found
+            && let Err(err) = sale.apply(&self.price)
+        {
+            violation(violations, join(path, "sale"), err.to_string());
+        }
+    }
+}
+
+impl Validate for Currency {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        if self.minor_units() < 0 {
+            violation(
+                violations,
+                path.to_string(),
+                format!("{self} must not be negative"),
+            );
+        }
+    }
+}
+
+impl Validate for Sale {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        match self {
+            Self::PercentOff(percent) => {
+                let path = join(path, "percent_off");
+                if *percent < Percent::ZERO {
+                    violation(violations, path, format!("{percent}% must not be negative"));
+                } else if *percent > Percent::from_major(100) {
+                    violation(violations, path, format!("{percent}% exceeds 100%"));
+                }
+            }
+            Self::AmountOff(amount) => amount.validate_at(&join(path, "amount_off"), violations),
+            Self::SalePrice(amount) => amount.validate_at(&join(path, "sale_price"), violations),
+        }
+    }
+}
@@ -0,0 +1,591 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+use derive_more::{Display, From};
+use quick_xml::{
+    DeError, NsReader, SeError, Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event, attributes::Attribute},
+    name::{NamespaceError, PrefixDeclaration, QName, ResolveResult},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::{
+    Product, flatten,
+    types::{
+        CurrencyEncoding, CurrencyNaming, FieldError, FieldErrorVariants, NoSale, ParseNumberError,
+        currency_attribute, iso4217, with_no_sale,
+    },
+    validate::{Validate, ValidationErrors},
+};
+
+/// Everything that can go wrong while reading or writing a [`Product`] as XML.
+#[derive(Debug, Display, From)]
+pub enum XmlError {
+    #[display("I/O error: {_0}")]
+    Io(std::io::Error),
+    #[display("malformed XML at byte {position} (line {line}): {source}")]
+    #[from(skip)]
+    Syntax {
+        position: u64,
+        line: usize,
+        source: quick_xml::Error,
+    },
+    #[display("unknown currency element <{_0}>")]
+    #[from(skip)]
+    UnknownCurrency(String),
+    #[display("invalid number {value:?} in <{element}>: {source}")]
+    #[from(skip)]
+    InvalidNumber {
+        element: String,
+        value: String,
+        source: ParseNumberError,
+    },
+    #[display("missing element <{_0}>")]
+    #[from(skip)]
+    MissingElement(String),
+    #[display("{}", FieldError::Duplicate { first: first.clone(), second: second.clone() })]
+    #[from(skip)]
+    DuplicateElement { first: String, second: String },
+    #[display("unexpected element <{_0}>")]
+    #[from(skip)]
+    UnexpectedElement(String),
+    #[display("expected root element <{expected}>, found <{found}>")]
+    #[from(skip)]
+    UnexpectedRoot { expected: String, found: String },
+    #[display("cannot write documents encoded as {_0:?}, only as UTF-8")]
+    #[from(skip)]
+    UnsupportedEncoding(String),
+    #[display("invalid product: {_0}")]
+    Invalid(ValidationErrors),
+    #[display("failed to deserialize: {_0}")]
+    Deserialize(DeError),
+    #[display("failed to serialize: {_0}")]
+    Serialize(SeError),
+}
+
+impl std::error::Error for XmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Syntax { source, .. } => Some(source),
+            Self::InvalidNumber { source, .. } => Some(source),
+            Self::Invalid(err) => Some(err),
+            Self::Deserialize(err) => Some(err),
+            Self::Serialize(err) => Some(err),
+            Self::UnknownCurrency(_)
+            | Self::MissingElement(_)
+            | Self::DuplicateElement { .. }
+            | Self::UnexpectedElement(_)
+            | Self::UnexpectedRoot { .. }
+            | Self::UnsupportedEncoding(_) => None,
+        }
+    }
+}
+
+impl XmlError {
+    /// Classifies a deserialization failure by the [`FieldError`] raised for it, if
+    /// any, using `input` to locate syntax errors.
+    fn from_de(err: DeError, field: Option<FieldError>, input: &str, position: u64) -> Self {
+        match err {
+            DeError::InvalidXml(quick_xml::Error::Io(io)) => {
+                Self::Io(std::io::Error::new(io.kind(), io.to_string()))
+            }
+            DeError::InvalidXml(source) => {
+                let line = line_at(input, position);
+                Self::Syntax {
+                    position,
+                    line,
+                    source,
+                }
+            }
+            err => FieldError::classify(field, || Self::Deserialize(err)),
+        }
+    }
+}
+
+impl FieldErrorVariants for XmlError {
+    fn unknown_currency(element: String) -> Self {
+        Self::UnknownCurrency(element)
+    }
+
+    fn invalid_number(element: String, value: String, source: ParseNumberError) -> Self {
+        Self::InvalidNumber {
+            element,
+            value,
+            source,
+        }
+    }
+
+    fn missing(element: String) -> Self {
+        Self::MissingElement(element)
+    }
+
+    fn duplicate(first: String, second: String) -> Self {
+        Self::DuplicateElement { first, second }
+    }
+
+    fn unexpected(element: String) -> Self {
+        Self::UnexpectedElement(element)
+    }
+}
+
+/// 1-based line number of the byte at `position`.
+fn line_at(input: &str, position: u64) -> usize {
+    let end = (position as usize).min(input.len());
+    input.as_bytes()[..end]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+        + 1
+}
+
+/// How documents are written by [`to_writer_with`] and [`to_string_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOptions {
+    /// Name of the document's root element.
+    pub root: String,
+    /// Whether to start the document with an `<?xml ...?>` declaration.
+    pub declaration: bool,
+    /// Value of the declaration's `encoding` attribute, if any. Documents are always
+    /// written as UTF-8, so writing a declaration with any other encoding fails with
+    /// [`XmlError::UnsupportedEncoding`].
+    pub encoding: Option<String>,
+    /// Pretty-print with this indentation; `None` writes everything on one line.
+    pub indent: Option<Indent>,
+    /// Line ending written between pretty-printed lines and after the declaration.
+    pub line_ending: LineEnding,
+    /// Write empty elements, such as the `<Sale>` of a product without a sale, as
+    /// `<Sale></Sale>` rather than `<Sale/>`.
+    pub expand_empty_elements: bool,
+    /// Whether USD and EUR are written as `<Dollars>`/`<Euros>` or by their ISO code.
+    pub currency_naming: CurrencyNaming,
+    /// Whether a product's price is written as `<Dollars>` or `<Price currency="USD">`.
+    pub currency_encoding: CurrencyEncoding,
+    /// Whether a product without a sale has no `<Sale>`, `<Sale/>` or `<Sale xsi:nil="true"/>`.
+    pub no_sale: NoSale,
+    /// Namespace to put the document's elements in, declared on the root element.
+    pub namespace: Option<Namespace>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            root: "Product".to_string(),
+            declaration: false,
+            encoding: Some("UTF-8".to_string()),
+            indent: None,
+            line_ending: LineEnding::Lf,
+            expand_empty_elements: false,
+            currency_naming: CurrencyNaming::Legacy,
+            currency_encoding: CurrencyEncoding::Element,
+            no_sale: NoSale::Empty,
+            namespace: None,
+        }
+    }
+}
+
+/// A namespace written documents put their elements in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namespace {
+    pub uri: String,
+    /// Prefix written on every element, e.g. `p` for `<p:Product xmlns:p="...">`;
+    /// `None` declares the default namespace, `<Product xmlns="...">`.
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Indent {
+    pub char: char,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// How documents are checked by [`from_reader_with`] and [`from_str_with`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadOptions {
+    /// Reject documents whose root element has a different name.
+    pub root: Option<String>,
+    /// Reject a second currency element, elements nothing reads and a repeated
+    /// `<Sale>`, instead of reading the first currency and ignoring the rest.
+    pub strict: bool,
+    /// Namespace URI our elements are in. Elements in it are matched by their local
+    /// name, whatever prefix the document binds it to, so `<p:Dollars>` reads as
+    /// `<Dollars>`, and its declarations are dropped. Elements in no namespace are
+    /// read as they are, and elements in other namespaces keep their prefixed name,
+    /// so `<x:Dollars>` is not taken for a price but ends up in
+    /// [`extra`](super::Product::extra). So does `<Dollars xmlns="urn:other">`, as
+    /// `ns1:Dollars`. `None` ignores prefixes altogether.
+    pub namespace: Option<String>,
+}
+
+/// Deserializes a `T` from an XML document held in memory.
+pub fn from_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T, XmlError> {
+    deserialize(input, &ReadOptions::default())
+}
+
+pub fn from_str_with<T: DeserializeOwned>(
+    input: &str,
+    options: &ReadOptions,
+) -> Result<T, XmlError> {
+    match &options.namespace {
+        Some(namespace) => deserialize(&local_names(input, namespace)?, options),
+        None => deserialize(input, options),
+    }
+}
+
+fn deserialize<'de, T: Deserialize<'de>>(
+    input: &'de str,
+    options: &ReadOptions,
+) -> Result<T, XmlError> {
+    if let Some(expected) = &options.root {
+        check_root(input, expected)?;
+    }
+    let mut de = quick_xml::de::Deserializer::from_str(input);
+
+    FieldError::catch(|| flatten::with_strict(options.strict, || T::deserialize(&mut de))).map_err(
+        |(err, field)| {
+            let position = de.get_ref().get_ref().error_position();
+            XmlError::from_de(err, field, input, position)
+        },
+    )
+}
+
+/// Rewrites `input` so that names in `namespace` are plain local names and names in
+/// other namespaces keep their prefix through deserialization.
+///
+/// quick-xml drops everything up to the first colon of a name, so `<x:Dollars>`
+/// would otherwise read as `<Dollars>` whatever `x` is bound to. Foreign names get
+/// their prefix doubled, `<x:x:Dollars>`, which quick-xml reads as `x:Dollars`.
+/// Unprefixed names in a foreign default namespace get a made-up prefix, `ns1`,
+/// `ns2`, ... per namespace, which replaces the `xmlns="..."` declaring it.
+fn local_names(input: &str, namespace: &str) -> Result<String, XmlError> {
+    let mut reader = NsReader::from_str(input);
+    let mut writer = Writer::new(Vec::new());
+    let mut renamer = Renamer {
+        namespace,
+        defaults: Vec::new(),
+    };
+    let syntax = |reader: &NsReader<&[u8]>, source| {
+        let position = reader.error_position();
+        XmlError::Syntax {
+            position,
+            line: line_at(input, position),
+            source,
+        }
+    };
+    loop {
+        let (resolved, event) = match reader.read_resolved_event() {
+            Ok((ResolveResult::Unknown(prefix), _)) => {
+                let source = quick_xml::Error::Namespace(NamespaceError::UnknownPrefix(prefix));
+                return Err(syntax(&reader, source));
+            }
+            Ok((resolved, event)) => (bound_namespace(resolved), event),
+            Err(source) => return Err(syntax(&reader, source)),
+        };
+        let event = match event {
+            Event::Start(start) => Event::Start(
+                renamer
+                    .rename_start(&reader, &start, resolved.as_deref())
+                    .map_err(|err| syntax(&reader, err))?,
+            ),
+            Event::Empty(start) => Event::Empty(
+                renamer
+                    .rename_start(&reader, &start, resolved.as_deref())
+                    .map_err(|err| syntax(&reader, err))?,
+            ),
+            Event::End(end) => Event::End(BytesEnd::new(
+                renamer.rename(end.name(), resolved.as_deref()),
+            )),
+            Event::Eof => break,
+            event => event,
+        };
+        writer.write_event(event)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()).expect("input was UTF-8"))
+}
+
+/// The namespace a name is bound to, if any.
+fn bound_namespace(resolved: ResolveResult) -> Option<Vec<u8>> {
+    match resolved {
+        ResolveResult::Bound(ns) => Some(ns.as_ref().to_vec()),
+        _ => None,
+    }
+}
+
+/// Names for [`local_names`], remembering the foreign default namespaces seen so far.
+struct Renamer<'a> {
+    namespace: &'a str,
+    defaults: Vec<Vec<u8>>,
+}
+
+impl Renamer<'_> {
+    /// The prefix standing in for `uri` as a default namespace.
+    fn default_prefix(&mut self, uri: &[u8]) -> String {
+        let index = match self.defaults.iter().position(|seen| seen == uri) {
+            Some(index) => index,
+            None => {
+                self.defaults.push(uri.to_vec());
+                self.defaults.len() - 1
+            }
+        };
+        format!("ns{}", index + 1)
+    }
+
+    /// The local name if `name` is in our namespace or none, else `name` with its
+    /// prefix doubled.
+    fn rename(&mut self, name: QName, bound: Option<&[u8]>) -> String {
+        let local = String::from_utf8_lossy(name.local_name().as_ref()).into_owned();
+        let prefix = match bound {
+            Some(ns) if ns != self.namespace.as_bytes() => match name.prefix() {
+                Some(prefix) => String::from_utf8_lossy(prefix.as_ref()).into_owned(),
+                None => self.default_prefix(ns),
+            },
+            _ => return local,
+        };
+        format!("{prefix}:{prefix}:{local}")
+    }
+
+    fn rename_start(
+        &mut self,
+        reader: &NsReader<&[u8]>,
+        start: &BytesStart,
+        bound: Option<&[u8]>,
+    ) -> Result<BytesStart<'static>, quick_xml::Error> {
+        let mut renamed = BytesStart::new(self.rename(start.name(), bound));
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            let key = match attribute.key.as_namespace_binding() {
+                // quick-xml keeps declarations as written; ours are no longer needed,
+                // and neither is undeclaring the default namespace.
+                Some(_)
+                    if attribute.value.as_ref() == self.namespace.as_bytes()
+                        || attribute.value.is_empty() =>
+                {
+                    continue;
+                }
+                Some(PrefixDeclaration::Default) => {
+                    format!("xmlns:{}", self.default_prefix(&attribute.value))
+                }
+                Some(PrefixDeclaration::Named(_)) => {
+                    String::from_utf8_lossy(attribute.key.as_ref()).into_owned()
+                }
+                None => {
+                    let (resolved, _) = reader.resolve_attribute(attribute.key);
+                    self.rename(attribute.key, bound_namespace(resolved).as_deref())
+                }
+            };
+            renamed.push_attribute(Attribute {
+                key: QName(key.as_bytes()),
+                value: attribute.value,
+            });
+        }
+        Ok(renamed)
+    }
+}
+
+/// Deserializes a `T` from an XML document read to the end of `reader`.
+pub fn from_reader<R: BufRead, T: DeserializeOwned>(reader: R) -> Result<T, XmlError> {
+    from_reader_with(reader, &ReadOptions::default())
+}
+
+pub fn from_reader_with<R: BufRead, T: DeserializeOwned>(
+    mut reader: R,
+    options: &ReadOptions,
+) -> Result<T, XmlError> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    from_str_with(&input, options)
+}
+
+/// quick-xml ignores the root element's name, so look it up separately.
+fn check_root(input: &str, expected: &str) -> Result<(), XmlError> {
+    let mut reader = quick_xml::Reader::from_str(input);
+    loop {
+        let found = match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) => e.name().as_ref().to_vec(),
+            Ok(Event::Eof) => return Err(XmlError::MissingElement(expected.to_string())),
+            Ok(_) => continue,
+            Err(source) => {
+                let position = reader.error_position();
+                return Err(XmlError::Syntax {
+                    position,
+                    line: line_at(input, position),
+                    source,
+                });
+            }
+        };
+        let found = String::from_utf8_lossy(&found);
+        return if found == expected {
+            Ok(())
+        } else {
+            Err(XmlError::UnexpectedRoot {
+                expected: expected.to_string(),
+                found: found.into_owned(),
+            })
+        };
+    }
+}
+
+/// Serializes `obj` as an XML document into `writer`.
+pub fn to_writer<W: Write, T: Serialize>(writer: W, obj: &T) -> Result<(), XmlError> {
+    to_writer_with(writer, obj, &WriteOptions::default())
+}
+
+pub fn to_writer_with<W: Write, T: Serialize>(
+    mut writer: W,
+    obj: &T,
+    options: &WriteOptions,
+) -> Result<(), XmlError> {
+    writer.write_all(to_string_with(obj, options)?.as_bytes())?;
+    Ok(())
+}
+
+/// Serializes `obj` as an XML document held in memory.
+pub fn to_string<T: Serialize>(obj: &T) -> Result<String, XmlError> {
+    to_string_with(obj, &WriteOptions::default())
+}
+
+pub fn to_string_with<T: Serialize>(obj: &T, options: &WriteOptions) -> Result<String, XmlError> {
+    let mut out = String::new();
+    if options.declaration {
+        out.push_str(r#"<?xml version="1.0""#);
+        if let Some(encoding) = &options.encoding {
+            if !["UTF-8", "UTF8"]
+                .iter()
+                .any(|utf8| encoding.eq_ignore_ascii_case(utf8))
+            {
+                return Err(XmlError::UnsupportedEncoding(encoding.clone()));
+            }
+            out.push_str(&format!(r#" encoding="{encoding}""#));
+        }
+        out.push_str("?>");
+        if options.indent.is_some() {
+            out.push('\n');
+        }
+    }
+
+    let mut body = String::new();
+    let mut serializer = quick_xml::se::Serializer::with_root(&mut body, Some(&options.root))?;
+    if let Some(indent) = options.indent {
+        serializer.indent(indent.char, indent.size);
+    }
+    serializer.expand_empty_elements(options.expand_empty_elements);
+    iso4217::with_naming(options.currency_naming, || {
+        currency_attribute::with_encoding(options.currency_encoding, || {
+            with_no_sale(options.no_sale, || obj.serialize(serializer))
+        })
+    })?;
+    match &options.namespace {
+        Some(namespace) => out.push_str(&qualified_names(&body, namespace)),
+        None => out.push_str(&body),
+    }
+
+    Ok(match options.line_ending {
+        LineEnding::Lf => out,
+        LineEnding::CrLf => crlf_line_endings(&out),
+    })
+}
+
+/// Turns the newlines quick-xml writes between elements into `\r\n`, leaving those
+/// inside text content as they are.
+fn crlf_line_endings(markup: &str) -> String {
+    let mut reader = quick_xml::Reader::from_str(markup);
+    let mut writer = Writer::new(Vec::new());
+    loop {
+        let event = match reader
+            .read_event()
+            .expect("serialized markup is well-formed")
+        {
+            Event::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {
+                let text = String::from_utf8_lossy(&text).replace('\n', "\r\n");
+                Event::Text(BytesText::from_escaped(text))
+            }
+            Event::Eof => break,
+            event => event,
+        };
+        writer
+            .write_event(event)
+            .expect("writing to a Vec cannot fail");
+    }
+    String::from_utf8(writer.into_inner()).expect("serialized markup is UTF-8")
+}
+
+/// Puts every element of `markup` that is not already prefixed in `namespace`, and
+/// declares it on the root element.
+fn qualified_names(markup: &str, namespace: &Namespace) -> String {
+    let mut reader = quick_xml::Reader::from_str(markup);
+    let mut writer = Writer::new(Vec::new());
+    let qualify = |name: QName| {
+        let name = String::from_utf8_lossy(name.as_ref());
+        match &namespace.prefix {
+            Some(prefix) if !name.contains(':') => format!("{prefix}:{name}"),
+            _ => name.into_owned(),
+        }
+    };
+    let mut root = true;
+    let mut qualify_start = |start: &BytesStart| {
+        let mut qualified = BytesStart::new(qualify(start.name()));
+        if std::mem::take(&mut root) {
+            let declaration = match &namespace.prefix {
+                Some(prefix) => format!("xmlns:{prefix}"),
+                None => "xmlns".to_string(),
+            };
+            qualified.push_attribute((declaration.as_str(), namespace.uri.as_str()));
+        }
+        qualified.extend_attributes(start.attributes().flatten());
+        qualified
+    };
+    loop {
+        let event = match reader
+            .read_event()
+            .expect("serialized markup is well-formed")
+        {
+            Event::Start(start) => Event::Start(qualify_start(&start)),
+            Event::Empty(start) => Event::Empty(qualify_start(&start)),
+            Event::End(end) => Event::End(BytesEnd::new(qualify(end.name()))),
+            Event::Eof => break,
+            event => event,
+        };
+        writer
+            .write_event(event)
+            .expect("writing to a Vec cannot fail");
+    }
+    String::from_utf8(writer.into_inner()).expect("serialized markup is UTF-8")
+}
+
+pub fn from_xml_file(file_path: impl Into<PathBuf>) -> Result<Product, XmlError> {
+    let source: File = File::open(file_path.into())?;
+    from_reader(BufReader::new(source))
+}
+
+/// Like [`from_xml_file`], but also rejects products that break a [`Validate`] rule.
+pub fn from_xml_file_validated(file_path: impl Into<PathBuf>) -> Result<Product, XmlError> {
+    let product = from_xml_file(file_path)?;
+    product.validate()?;
+    Ok(product)
+}
+
+pub fn to_xml_file(file_path: impl Into<PathBuf>, obj: &Product) -> Result<File, XmlError> {
+    to_xml_file_with(file_path, obj, &WriteOptions::default())
+}
+
+/// Like [`to_xml_file`], e.g. pretty-printed with [`WriteOptions::indent`].
+pub fn to_xml_file_with(
+    file_path: impl Into<PathBuf>,
+    obj: &Product,
+    options: &WriteOptions,
+) -> Result<File, XmlError> {
+    let file: File = File::create(file_path.into())?;
+    to_writer_with(&file, obj, options)?;
+
+    Ok(file)
+}
@@ -0,0 +1,271 @@
+//! XML Schema for the documents written by [`xml`](super::xml) and
+//! [`CatalogWriter`](super::catalog::CatalogWriter).
+//!
+//! The schema declares both `<Product>` and `<Catalog>` as root elements, in no
+//! namespace or in the one documents were written to, whatever its prefix. The
+//! flattened [`Currency`](super::types::Currency) becomes a `Currency` group holding
+//! an `xs:choice` between its legacy elements (`<Dollars>`, `<Euros>`) and one
+//! element per ISO 4217 code, so documents written under either
+//! [`CurrencyNaming`](super::types::CurrencyNaming) validate. A product's price may
+//! instead be a `<Price>` with a `currency` attribute, as written under
+//! [`CurrencyEncoding::Attribute`](super::types::CurrencyEncoding::Attribute).
+//!
+//! Elements kept in a product's [`extra`](super::Product::extra) are written after
+//! `<Sale>`, and the schema allows them there only when they are in another
+//! namespace, such as `<x:Color xmlns:x="urn:other">`. A wildcard that also matched
+//! unqualified elements would match `<Sale>` too, which XSD 1.0 forbids, so products
+//! with unqualified extras, which only lenient reading accepts, do not validate.
+//!
+//! `<Sale>` is optional, and may be empty or `xsi:nil`, so every
+//! [`NoSale`](super::types::NoSale) policy validates.
+
+use std::{collections::BTreeSet, io};
+
+use quick_xml::{Writer, events::BytesDecl, events::Event};
+
+use super::{
+    catalog,
+    types::{LEGACY_NAMES, currency_attribute, iso4217::CURRENCIES},
+};
+
+const XS_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// Name of the simple type for decimals with `scale` fraction digits.
+fn decimal_type(scale: u8) -> String {
+    format!("Decimal{scale}")
+}
+
+/// Returns the XSD describing product and catalog documents, with their elements in
+/// `namespace` if given, as written with [`WriteOptions::namespace`], or else in no
+/// namespace.
+///
+/// [`WriteOptions::namespace`]: super::xml::WriteOptions::namespace
+pub fn schema(namespace: Option<&str>) -> String {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    write_schema(&mut writer, namespace).expect("writing to a Vec cannot fail");
+    String::from_utf8(writer.into_inner()).expect("the schema is UTF-8")
+}
+
+fn write_schema(w: &mut Writer<Vec<u8>>, namespace: Option<&str>) -> io::Result<()> {
+    w.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    let mut root = w
+        .create_element("xs:schema")
+        .with_attribute(("xmlns:xs", XS_NAMESPACE));
+    if let Some(namespace) = namespace {
+        // The default namespace makes references such as `type="Product"` resolve
+        // to the types declared here.
+        root = root.with_attributes([
+            ("targetNamespace", namespace),
+            ("xmlns", namespace),
+            ("elementFormDefault", "qualified"),
+        ]);
+    }
+    root.write_inner_content(|w| {
+        w.create_element("xs:element")
+            .with_attributes([("name", catalog::PRODUCT), ("type", "Product")])
+            .write_empty()?;
+        w.create_element("xs:element")
+            .with_attribute(("name", catalog::ROOT))
+            .write_inner_content(|w| {
+                w.create_element("xs:complexType")
+                    .write_inner_content(|w| {
+                        w.create_element("xs:sequence").write_inner_content(|w| {
+                            w.create_element("xs:element")
+                                .with_attributes([
+                                    ("name", catalog::PRODUCT),
+                                    ("type", "Product"),
+                                    ("minOccurs", "0"),
+                                    ("maxOccurs", "unbounded"),
+                                ])
+                                .write_empty()?;
+                            Ok(())
+                        })?;
+                        Ok(())
+                    })?;
+                Ok(())
+            })?;
+
+        write_product_type(w)?;
+        write_sale_type(w)?;
+        write_currency_group(w)?;
+        write_price_type(w)?;
+        write_decimal_types(w)
+    })?;
+    Ok(())
+}
+
+fn write_product_type(w: &mut Writer<Vec<u8>>) -> io::Result<()> {
+    w.create_element("xs:complexType")
+        .with_attribute(("name", "Product"))
+        .write_inner_content(|w| {
+            w.create_element("xs:sequence").write_inner_content(|w| {
+                w.create_element("xs:element")
+                    .with_attributes([("name", "Name"), ("type", "xs:string")])
+                    .write_empty()?;
+                w.create_element("xs:choice").write_inner_content(|w| {
+                    w.create_element("xs:group")
+                        .with_attribute(("ref", "Currency"))
+                        .write_empty()?;
+                    w.create_element("xs:element")
+                        .with_attributes([("name", currency_attribute::ELEMENT), ("type", "Price")])
+                        .write_empty()?;
+                    Ok(())
+                })?;
+                w.create_element("xs:element")
+                    .with_attributes([
+                        ("name", "Sale"),
+                        ("type", "Sale"),
+                        ("minOccurs", "0"),
+                        ("nillable", "true"),
+                    ])
+                    .write_empty()?;
+                // Extra elements, see the module docs for why only foreign ones.
+                w.create_element("xs:any")
+                    .with_attributes([
+                        ("namespace", "##other"),
+                        ("processContents", "lax"),
+                        ("minOccurs", "0"),
+                        ("maxOccurs", "unbounded"),
+                    ])
+                    .write_empty()?;
+                Ok(())
+            })?;
+            w.create_element("xs:anyAttribute")
+                .with_attributes([("namespace", "##other"), ("processContents", "lax")])
+                .write_empty()?;
+            Ok(())
+        })?;
+    Ok(())
+}
+
+/// `<Sale>` holds at most one variant element; empty means no sale.
+fn write_sale_type(w: &mut Writer<Vec<u8>>) -> io::Result<()> {
+    w.create_element("xs:complexType")
+        .with_attribute(("name", "Sale"))
+        .write_inner_content(|w| {
+            w.create_element("xs:choice")
+                .with_attribute(("minOccurs", "0"))
+                .write_inner_content(|w| {
+                    w.create_element("xs:element")
+                        .with_attributes([("name", "PercentOff"), ("type", &*decimal_type(2))])
+                        .write_empty()?;
+                    for name in ["AmountOff", "SalePrice"] {
+                        w.create_element("xs:element")
+                            .with_attributes([("name", name), ("type", "CurrencyAmount")])
+                            .write_empty()?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+    w.create_element("xs:complexType")
+        .with_attribute(("name", "CurrencyAmount"))
+        .write_inner_content(|w| {
+            w.create_element("xs:group")
+                .with_attribute(("ref", "Currency"))
+                .write_empty()?;
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_currency_group(w: &mut Writer<Vec<u8>>) -> io::Result<()> {
+    w.create_element("xs:group")
+        .with_attribute(("name", "Currency"))
+        .write_inner_content(|w| {
+            w.create_element("xs:choice").write_inner_content(|w| {
+                for (legacy, alpha) in LEGACY_NAMES {
+                    let code = CURRENCIES
+                        .iter()
+                        .find(|code| code.alpha == *alpha)
+                        .expect("legacy currencies are listed");
+                    w.create_element("xs:element")
+                        .with_attributes([
+                            ("name", *legacy),
+                            ("type", &*decimal_type(code.minor_units)),
+                        ])
+                        .write_empty()?;
+                }
+                for code in CURRENCIES {
+                    w.create_element("xs:element")
+                        .with_attributes([
+                            ("name", code.alpha),
+                            ("type", &*decimal_type(code.minor_units)),
+                        ])
+                        .write_empty()?;
+                }
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+    Ok(())
+}
+
+/// `<Price currency="USD">6.50</Price>`. The fraction digits depend on the currency,
+/// which XSD 1.0 cannot express, so any decimal is allowed here.
+fn write_price_type(w: &mut Writer<Vec<u8>>) -> io::Result<()> {
+    let attribute = currency_attribute::ATTRIBUTE.trim_start_matches('@');
+    w.create_element("xs:complexType")
+        .with_attribute(("name", "Price"))
+        .write_inner_content(|w| {
+            w.create_element("xs:simpleContent")
+                .write_inner_content(|w| {
+                    w.create_element("xs:extension")
+                        .with_attribute(("base", "xs:decimal"))
+                        .write_inner_content(|w| {
+                            w.create_element("xs:attribute")
+                                .with_attributes([
+                                    ("name", attribute),
+                                    ("type", "CurrencyName"),
+                                    ("use", "required"),
+                                ])
+                                .write_empty()?;
+                            Ok(())
+                        })?;
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+    // Legacy names are read too, though codes are what gets written.
+    w.create_element("xs:simpleType")
+        .with_attribute(("name", "CurrencyName"))
+        .write_inner_content(|w| {
+            w.create_element("xs:restriction")
+                .with_attribute(("base", "xs:string"))
+                .write_inner_content(|w| {
+                    let legacy = LEGACY_NAMES.iter().map(|(legacy, _)| *legacy);
+                    for name in legacy.chain(CURRENCIES.iter().map(|code| code.alpha)) {
+                        w.create_element("xs:enumeration")
+                            .with_attribute(("value", name))
+                            .write_empty()?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_decimal_types(w: &mut Writer<Vec<u8>>) -> io::Result<()> {
+    let scales: BTreeSet<u8> = CURRENCIES
+        .iter()
+        .map(|code| code.minor_units)
+        .chain([2])
+        .collect();
+    for scale in scales {
+        w.create_element("xs:simpleType")
+            .with_attribute(("name", &*decimal_type(scale)))
+            .write_inner_content(|w| {
+                w.create_element("xs:restriction")
+                    .with_attribute(("base", "xs:decimal"))
+                    .write_inner_content(|w| {
+                        w.create_element("xs:fractionDigits")
+                            .with_attribute(("value", &*scale.to_string()))
+                            .write_empty()?;
+                        Ok(())
+                    })?;
+                Ok(())
+            })?;
+    }
+    Ok(())
+}
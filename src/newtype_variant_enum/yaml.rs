@@ -0,0 +1,192 @@
+//! Reading and writing products as YAML, for hand-edited fixtures:
+//!
+//! ```yaml
+//! Name: Yo-yo
+//! Dollars: 6.0
+//! Sale:
+//!   PercentOff: 25.5
+//! ```
+//!
+//! The currency key sits at the top level, as in [`json`](super::json), and comes
+//! from the same `Serialize`/`Deserialize` impls. A product without a sale has
+//! `Sale: null`, or no `Sale` key at all.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use derive_more::{Display, From};
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{
+    Product,
+    types::{
+        CurrencyNaming, FieldError, FieldErrorVariants, ParseNumberError, amount,
+        describe_duplicate, iso4217,
+    },
+};
+
+/// Everything that can go wrong while reading or writing a [`Product`] as YAML.
+#[derive(Debug, Display, From)]
+pub enum YamlError {
+    #[display("I/O error: {_0}")]
+    Io(std::io::Error),
+    #[display("malformed YAML at line {line}, column {column}: {source}")]
+    #[from(skip)]
+    Syntax {
+        line: usize,
+        column: usize,
+        source: serde_yaml::Error,
+    },
+    #[display("unknown currency key {_0:?}")]
+    #[from(skip)]
+    UnknownCurrency(String),
+    #[display("invalid number {value:?} in {key:?}: {source}")]
+    #[from(skip)]
+    InvalidNumber {
+        key: String,
+        value: String,
+        source: ParseNumberError,
+    },
+    #[display("missing key {_0:?}")]
+    #[from(skip)]
+    MissingKey(String),
+    #[display("{}", describe_duplicate("key", first, second))]
+    #[from(skip)]
+    DuplicateKey { first: String, second: String },
+    #[display("unexpected key {_0:?}")]
+    #[from(skip)]
+    UnexpectedKey(String),
+    #[display("failed to deserialize: {_0}")]
+    #[from(skip)]
+    Deserialize(serde_yaml::Error),
+    #[display("failed to serialize: {_0}")]
+    #[from(skip)]
+    Serialize(serde_yaml::Error),
+}
+
+impl std::error::Error for YamlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Syntax { source, .. } => Some(source),
+            Self::InvalidNumber { source, .. } => Some(source),
+            Self::Deserialize(err) | Self::Serialize(err) => Some(err),
+            Self::UnknownCurrency(_)
+            | Self::MissingKey(_)
+            | Self::DuplicateKey { .. }
+            | Self::UnexpectedKey(_) => None,
+        }
+    }
+}
+
+impl YamlError {
+    /// Classifies a deserialization failure by the [`FieldError`] raised for it, if
+    /// any. serde_yaml does not tell syntax errors apart, so a failure is one if
+    /// `input` is not a YAML document at all.
+    fn from_de(input: &str, (err, field): (serde_yaml::Error, Option<FieldError>)) -> Self {
+        FieldError::classify(field, || {
+            match (
+                serde_yaml::from_str::<serde_yaml::Value>(input),
+                err.location(),
+            ) {
+                (Err(_), Some(location)) => Self::Syntax {
+                    line: location.line(),
+                    column: location.column(),
+                    source: err,
+                },
+                _ => Self::Deserialize(err),
+            }
+        })
+    }
+}
+
+impl FieldErrorVariants for YamlError {
+    fn unknown_currency(key: String) -> Self {
+        Self::UnknownCurrency(key)
+    }
+
+    fn invalid_number(key: String, value: String, source: ParseNumberError) -> Self {
+        Self::InvalidNumber { key, value, source }
+    }
+
+    fn missing(key: String) -> Self {
+        Self::MissingKey(key)
+    }
+
+    fn duplicate(first: String, second: String) -> Self {
+        Self::DuplicateKey { first, second }
+    }
+
+    fn unexpected(key: String) -> Self {
+        Self::UnexpectedKey(key)
+    }
+}
+
+/// How documents are written by [`to_yaml_writer_with`] and [`to_yaml_string_with`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteOptions {
+    /// Whether USD and EUR are written as `Dollars`/`Euros` or by their ISO code.
+    pub currency_naming: CurrencyNaming,
+}
+
+/// Deserializes a `T` from a YAML document held in memory.
+pub fn from_yaml_str<T: DeserializeOwned>(input: &str) -> Result<T, YamlError> {
+    FieldError::catch(|| serde_yaml::from_str(input)).map_err(|err| YamlError::from_de(input, err))
+}
+
+/// Deserializes a `T` from a YAML document read from `reader`.
+pub fn from_yaml_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T, YamlError> {
+    // serde_yaml reads the whole document before parsing it anyway.
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    from_yaml_str(&input)
+}
+
+/// Serializes `obj` as a YAML document into `writer`.
+pub fn to_yaml_writer<W: Write, T: Serialize>(writer: W, obj: &T) -> Result<(), YamlError> {
+    to_yaml_writer_with(writer, obj, &WriteOptions::default())
+}
+
+pub fn to_yaml_writer_with<W: Write, T: Serialize>(
+    mut writer: W,
+    obj: &T,
+    options: &WriteOptions,
+) -> Result<(), YamlError> {
+    // serde_yaml hides the I/O errors of a writer it is given, so it writes into
+    // memory and the I/O happens here.
+    writer.write_all(to_yaml_string_with(obj, options)?.as_bytes())?;
+    Ok(())
+}
+
+/// Serializes `obj` as a YAML document held in memory.
+pub fn to_yaml_string<T: Serialize>(obj: &T) -> Result<String, YamlError> {
+    to_yaml_string_with(obj, &WriteOptions::default())
+}
+
+pub fn to_yaml_string_with<T: Serialize>(
+    obj: &T,
+    options: &WriteOptions,
+) -> Result<String, YamlError> {
+    iso4217::with_naming(options.currency_naming, || {
+        amount::with_numbers(|| serde_yaml::to_string(obj))
+    })
+    .map_err(YamlError::Serialize)
+}
+
+pub fn from_yaml_file(file_path: impl Into<PathBuf>) -> Result<Product, YamlError> {
+    let source = File::open(file_path.into())?;
+    from_yaml_reader(BufReader::new(source))
+}
+
+pub fn to_yaml_file(file_path: impl Into<PathBuf>, obj: &Product) -> Result<File, YamlError> {
+    let file = File::create(file_path.into())?;
+    let mut writer = BufWriter::new(&file);
+    to_yaml_writer(&mut writer, obj)?;
+    writer.flush()?;
+    drop(writer);
+
+    Ok(file)
+}
@@ -0,0 +1,207 @@
+//! Runs the command-line tool as a user would and checks its output and exit codes.
+
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+use pretty_assertions::assert_eq;
+
+const YOYO: &str = "<Product><Name>Yo-yo</Name><Dollars>6</Dollars><Sale/></Product>";
+
+/// Runs the tool with `args`, feeding it `stdin`.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_newtype_enum_variant"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("should have started the tool");
+    // Commands that fail early exit without reading their input.
+    match child.stdin.take().unwrap().write_all(stdin.as_bytes()) {
+        Err(error) if error.kind() != ErrorKind::BrokenPipe => {
+            panic!("should have written stdin: {error}")
+        }
+        _ => {}
+    }
+    child.wait_with_output().expect("should have run the tool")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Where a test writes its fixture `file_name`, unique to this test run.
+fn temp_path(file_name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "newtype_enum_variant-cli-{}-{file_name}",
+        std::process::id()
+    ))
+}
+
+#[test]
+fn convert_between_stdin_and_stdout() {
+    let output = run(&["convert", "--from", "xml", "--to", "json"], YOYO);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "{\n  \"Name\": \"Yo-yo\",\n  \"Dollars\": 6.0,\n  \"Sale\": null\n}\n"
+    );
+
+    let output = run(
+        &["convert", "--from", "json", "--to", "xml", "-", "-"],
+        &stdout(&output),
+    );
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "<Product>\n  <Name>Yo-yo</Name>\n  <Dollars>6.00</Dollars>\n  <Sale/>\n</Product>\n"
+    );
+}
+
+#[test]
+fn convert_files_by_extension() {
+    let input = temp_path("convert.xml");
+    let output_path = temp_path("convert.csv");
+    fs::write(
+        &input,
+        "<Catalog><Product><Name>Yo-yo</Name><Dollars>6</Dollars><Sale/></Product>\
+         <Product><Name>Top</Name><Euros>2.5</Euros><Sale>10</Sale></Product></Catalog>",
+    )
+    .unwrap();
+
+    let output = run(
+        &[
+            "convert",
+            input.to_str().unwrap(),
+            output_path.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        fs::read_to_string(&output_path).unwrap(),
+        "Name,Currency,Amount,Sale\nYo-yo,Dollars,6.00,\nTop,Euros,2.50,10.00\n"
+    );
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output_path).unwrap();
+}
+
+#[test]
+fn validate_reports_invalid_products() {
+    let valid = temp_path("valid.xml");
+    fs::write(&valid, YOYO).unwrap();
+    let output = run(&["validate", valid.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1 product valid\n");
+    fs::remove_file(&valid).unwrap();
+
+    let invalid = "<Product><Name></Name><Dollars>-6</Dollars><Sale/></Product>";
+    let output = run(&["validate", "--format", "xml", "-"], invalid);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains("1 of 1 product invalid"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn show_and_roundtrip_succeed() {
+    let output = run(&["show", "--format", "xml", "-"], YOYO);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(
+        stdout(&output).starts_with("Yo-yo\n"),
+        "{}",
+        stdout(&output)
+    );
+
+    let output = run(
+        &["roundtrip", "--format", "json", "-"],
+        r#"{"Name": "Yo-yo", "USD": 6}"#,
+    );
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1 product survived the round trip\n");
+}
+
+#[test]
+fn bad_command_lines_exit_with_2() {
+    for args in [
+        &[][..],
+        &["frobnicate"],
+        &["convert", "--to"],
+        &["convert", "--colour", "red"],
+        &["convert", "--from", "pdf"],
+        &["validate"],
+        &["validate", "-"],
+    ] {
+        let output = run(args, YOYO);
+        assert_eq!(
+            output.status.code(),
+            Some(2),
+            "{args:?}: {}",
+            stderr(&output)
+        );
+        assert!(stderr(&output).contains("Usage:"), "{}", stderr(&output));
+    }
+}
+
+#[test]
+fn missing_files_exit_with_3() {
+    let output = run(&["validate", "does-not-exist.xml"], "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(
+        stderr(&output).starts_with("error: I/O error"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn unreadable_input_exits_with_4() {
+    for (format, input) in [
+        (
+            "xml",
+            "<Product><Name>Yo-yo</Name><Euros>1</Dollars></Product>",
+        ),
+        (
+            "xml",
+            "<Product><Name>Yo-yo</Name><Pesos>1</Pesos></Product>",
+        ),
+        ("json", r#"{"Name": "Yo-yo", "#),
+        ("csv", "Name,Currency,Amount\nYo-yo,Dollars,lots\n"),
+    ] {
+        let output = run(&["convert", "--from", format, "--to", "json"], input);
+        assert_eq!(
+            output.status.code(),
+            Some(4),
+            "{input}: {}",
+            stderr(&output)
+        );
+    }
+}
+
+#[test]
+fn usage_lists_the_formats_compiled_in() {
+    let output = run(&["help"], "");
+    assert_eq!(output.status.code(), Some(0));
+    let usage = stdout(&output);
+    assert_eq!(usage.contains("toml"), cfg!(feature = "toml"), "{usage}");
+    assert_eq!(usage.contains("yaml"), cfg!(feature = "yaml"), "{usage}");
+
+    for (format, enabled) in [
+        ("toml", cfg!(feature = "toml")),
+        ("yaml", cfg!(feature = "yaml")),
+    ] {
+        let output = run(&["convert", "--from", "xml", "--to", format], YOYO);
+        let expected = if enabled { 0 } else { 2 };
+        assert_eq!(output.status.code(), Some(expected), "{}", stderr(&output));
+    }
+}